@@ -0,0 +1,91 @@
+use macroquad::prelude::get_time;
+
+// Measured on heart_beat.ogg: 24 beats in the 32 seconds of the loop,
+// the first one a few milliseconds after the start
+const HEARTBEAT_BPM: f64 = 45.0;
+const HEARTBEAT_OFFSET: f64 = 0.03;
+
+// Distance to the nearest beat, in seconds
+const PERFECT_WINDOW: f64 = 0.08;
+const GOOD_WINDOW: f64 = 0.18;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BeatGrade {
+    Perfect,
+    Good,
+    Miss,
+}
+
+impl BeatGrade {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BeatGrade::Perfect => "Perfect",
+            BeatGrade::Good => "Good",
+            BeatGrade::Miss => "Miss",
+        }
+    }
+
+    /// Damage of an attack landing with this grade
+    pub fn apply(&self, damage: i32) -> i32 {
+        match self {
+            BeatGrade::Perfect => 2 * damage,
+            BeatGrade::Good => damage + 1,
+            BeatGrade::Miss => damage,
+        }
+    }
+
+    pub fn score(&self) -> i32 {
+        match self {
+            BeatGrade::Perfect => 50,
+            BeatGrade::Good => 20,
+            BeatGrade::Miss => 0,
+        }
+    }
+}
+
+/// Follows the heartbeat track, started with it
+pub struct BeatClock {
+    bpm: f64,
+    offset: f64,
+    start: f64,
+}
+
+impl BeatClock {
+    pub fn heartbeat() -> Self {
+        Self {
+            bpm: HEARTBEAT_BPM,
+            offset: HEARTBEAT_OFFSET,
+            start: get_time(),
+        }
+    }
+
+    /// To call when the track starts playing
+    pub fn start(&mut self) {
+        self.start = get_time();
+    }
+
+    fn period(&self) -> f64 {
+        60.0 / self.bpm
+    }
+
+    /// Position between two beats: 0 on a beat, going up to 1 before the next one
+    pub fn phase(&self) -> f32 {
+        let elapsed = get_time() - self.start - self.offset;
+        (elapsed / self.period()).rem_euclid(1.0) as f32
+    }
+
+    /// Grade an action happening now against the nearest beat
+    pub fn grade(&self) -> BeatGrade {
+        let phase = self.phase() as f64;
+        let distance = phase.min(1.0 - phase) * self.period();
+        if distance <= PERFECT_WINDOW {
+            BeatGrade::Perfect
+        }
+        else if distance <= GOOD_WINDOW {
+            BeatGrade::Good
+        }
+        else {
+            BeatGrade::Miss
+        }
+    }
+}
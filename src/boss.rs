@@ -0,0 +1,262 @@
+use macroquad::{prelude::*, rand::gen_range};
+
+use crate::enemy::{Enemy, EnemyEvent, EnemyId, Hit, Surroundings};
+use crate::game::TextureName;
+use crate::sprite::{AnimatedSprite, AnimationData};
+use crate::wave::EnemyKind;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+enum BossState {
+    Birth,
+    Drift,
+    DiveWindUp,
+    Dive,
+    Transition,
+    Dead,
+}
+
+// The boss is a ghost drawn twice bigger
+const BOSS_SCALE: f32 = 2.0;
+const SIZE: f32 = 64.0 * BOSS_SCALE;
+
+// Body parts, in the unflipped sprite
+const HURTBOXES: [Rect; 3] = [
+    Rect { x: 58.0, y: 38.0, w: 22.0, h: 22.0 },    // Head
+    Rect { x: 54.0, y: 60.0, w: 24.0, h: 20.0 },    // Body
+    Rect { x: 46.0, y: 80.0, w: 20.0, h: 14.0 },    // Tail
+];
+
+const DRIFT_Y: f32 = -15.0;
+const DIVE_WIND_UP: i32 = 50;
+const TRANSITION_TIME: i32 = 120;
+const HIT_FLASH: i32 = 20;
+// Breath taken from the hero by its body
+const DAMAGE: i32 = 240;
+
+/// The last spirit: a giant ghost fought in three phases
+pub struct Boss {
+    id: EnemyId,
+    position: Vec2,
+    velocity: Vec2,
+    pub sprite: AnimatedSprite,
+    state: BossState,
+    phase: usize,
+    timer: i32,
+    drift: f32,
+    dive_target: Vec2,
+
+    health: i32,
+    max_health: i32,
+    hit_flash: i32,
+
+    active: bool,
+}
+
+impl Boss {
+    pub fn new(id: EnemyId, x: f32, y: f32, health: i32) -> Self {
+        let position = Vec2 { x, y };
+        let mut sprite = AnimatedSprite::new(&Self::animation(BossState::Birth));
+        sprite.set_position_to(position);
+
+        Self {
+            id,
+            position,
+            velocity: Vec2::ZERO,
+            sprite,
+            state: BossState::Birth,
+            phase: 1,
+            timer: 0,
+            drift: 0.0,
+            dive_target: Vec2::ZERO,
+
+            health,
+            max_health: health,
+            hit_flash: 0,
+
+            active: true,
+        }
+    }
+
+    fn animation(state: BossState) -> AnimationData {
+        match state {
+            BossState::Birth => AnimationData{x: 0, y: 192, h: 64, w: 64, frames: 13, speed: 10, pivot_x: 0, pivot_y: 0},
+            BossState::Drift | BossState::Dive => AnimationData{x: 0, y: 0, h: 64, w: 64, frames: 5, speed: 8, pivot_x: 0, pivot_y: 0},
+            BossState::DiveWindUp => AnimationData{x: 0, y: 0, h: 64, w: 64, frames: 5, speed: 2, pivot_x: 0, pivot_y: 0},
+            BossState::Transition => AnimationData{x: 0, y: 64, h: 64, w: 64, frames: 10, speed: 4, pivot_x: 0, pivot_y: 0},
+            BossState::Dead => AnimationData{x: 0, y: 128, h: 64, w: 64, frames: 10, speed: 12, pivot_x: 0, pivot_y: 0},
+        }
+    }
+
+    fn dive_delay(&self) -> i32 {
+        match self.phase {
+            1 => gen_range(200, 260),
+            2 => gen_range(140, 200),
+            _ => gen_range(90, 140),
+        }
+    }
+}
+
+impl Enemy for Boss {
+    fn texture(&self) -> TextureName {
+        TextureName::Ghost
+    }
+
+    fn id(&self) -> EnemyId {
+        self.id
+    }
+
+    /// Summon ghosts at the end of a phase transition
+    fn update(&mut self, surroundings: &Surroundings) -> Vec<EnemyEvent> {
+        let hero_pos = surroundings.hero_pos;
+        let previous_state = self.state;
+        let mut summons = Vec::new();
+
+        if self.hit_flash > 0 {
+            self.hit_flash -= 1;
+            self.sprite.set_transparency(if self.hit_flash % 6 < 3 {0.6} else {1.0});
+        }
+
+        // The center of the boss body is over the hero
+        let hero_target = hero_pos - Vec2 { x: 0.5 * SIZE - 32.0, y: 0.0 };
+
+        match self.state {
+            BossState::Birth => {
+                if self.sprite.is_animation_ended() {
+                    self.state = BossState::Drift;
+                    self.timer = self.dive_delay();
+                }
+            },
+            BossState::Drift => {
+                // Slow swing across the arena, faster in each phase
+                self.drift += 0.008 * self.phase as f32;
+                let target = Vec2 { x: 149.0 + 150.0 * self.drift.sin(), y: DRIFT_Y + 6.0 * (3.0 * self.drift).sin() };
+                self.velocity = 0.05 * (target - self.position);
+                self.timer -= 1;
+                if self.timer <= 0 {
+                    self.state = BossState::DiveWindUp;
+                    self.timer = DIVE_WIND_UP;
+                    self.dive_target = Vec2 { x: hero_target.x.clamp(-40.0, 340.0), y: 8.0 };
+                }
+            },
+            BossState::DiveWindUp => {
+                // Rise a bit before falling on the hero
+                self.velocity = Vec2 { x: 0.0, y: -0.3 };
+                self.timer -= 1;
+                if self.timer <= 0 {
+                    self.state = BossState::Dive;
+                }
+            },
+            BossState::Dive => {
+                let to_target = self.dive_target - self.position;
+                self.velocity = to_target.clamp_length_max(2.0 + self.phase as f32);
+                if to_target.length() < 2.0 {
+                    self.state = BossState::Drift;
+                    self.timer = self.dive_delay();
+                }
+            },
+            BossState::Transition => {
+                // Back to the top, flashing, then call for help
+                self.velocity = 0.05 * (Vec2 { x: 149.0, y: DRIFT_Y - 10.0 } - self.position);
+                self.sprite.set_transparency(if self.timer % 10 < 5 {0.4} else {1.0});
+                self.timer -= 1;
+                if self.timer <= 0 {
+                    self.sprite.set_transparency(1.0);
+                    for _i in 0..self.phase {
+                        summons.push(EnemyEvent::Summon(EnemyKind::Ghost, Vec2 { x: gen_range(50.0, 380.0), y: 52.0 }));
+                    }
+                    self.state = BossState::Drift;
+                    self.timer = self.dive_delay();
+                }
+            },
+            BossState::Dead => {
+                self.velocity = Vec2 { x: 0.0, y: 0.2 };
+                if self.sprite.is_animation_ended() {
+                    self.active = false;
+                }
+            },
+        }
+
+        self.position += self.velocity;
+        self.sprite.flip_x = hero_target.x < self.position.x;
+
+        if previous_state != self.state {
+            self.sprite.set_animation(&Self::animation(self.state));
+            self.sprite.play();
+        }
+        self.sprite.set_position_to(self.position);
+
+        summons
+    }
+
+    /// Body parts in world space, mirrored when the sprite is flipped
+    fn get_hurtboxes(&self) -> Vec<Rect> {
+        HURTBOXES.iter().map(|b| {
+            let x = if self.sprite.flip_x {SIZE - b.x - b.w} else {b.x};
+            Rect { x: self.position.x + x, y: self.position.y + b.y, w: b.w, h: b.h }
+        }).collect()
+    }
+
+    fn position(&self) -> Vec2 {
+        // Same origin as a regular 64 pixels sprite, for the knockback direction
+        self.position + Vec2 { x: 0.5 * SIZE - 32.0, y: 0.5 * SIZE - 32.0 }
+    }
+
+    /// Too big to be pushed around
+    fn hit(&mut self, hit: Hit) {
+        let previous_state = self.state;
+        self.health -= hit.damage;
+        self.hit_flash = HIT_FLASH;
+
+        if self.health <= 0 {
+            self.state = BossState::Dead;
+        }
+        else {
+            // Phases start at two thirds and one third of the health
+            let phase = 1 + 3 * (self.max_health - self.health) as usize / self.max_health as usize;
+            if phase > self.phase {
+                self.phase = phase;
+                self.state = BossState::Transition;
+                self.timer = TRANSITION_TIME;
+            }
+        }
+        // Changed outside of update, which only sees its own transitions
+        if previous_state != self.state {
+            self.sprite.set_animation(&Self::animation(self.state));
+            self.sprite.play();
+        }
+    }
+
+    fn is_hitable(&self) -> bool {
+        matches!(self.state, BossState::Drift | BossState::DiveWindUp | BossState::Dive)
+    }
+
+    fn is_harmful(&self) -> bool {
+        matches!(self.state, BossState::Drift | BossState::DiveWindUp | BossState::Dive)
+    }
+
+    fn get_damage(&self) -> i32 {
+        DAMAGE
+    }
+
+    fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn sprite_mut(&mut self) -> &mut AnimatedSprite {
+        &mut self.sprite
+    }
+
+    /// Drawn bigger around its own position
+    fn draw(&mut self, texture: Texture2D) {
+        self.sprite.draw_sprite(texture, self.position * (BOSS_SCALE - 1.0), BOSS_SCALE);
+    }
+
+    fn get_health_bar(&self) -> Option<(i32, i32)> {
+        Some((self.health.max(0), self.max_health))
+    }
+
+    /// The new phase, during the transition
+    fn get_announcement(&self) -> Option<String> {
+        if self.state == BossState::Transition {Some(format!("- Phase {} -", self.phase))} else {None}
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use macroquad::{prelude::*, rand::gen_range};
+
+use crate::enemy::{Enemy, EnemyEvent, EnemyId, Hit, Surroundings};
+use crate::game::TextureName;
+use crate::physics::Body;
+use crate::sprite::{AnimatedSprite, AnimationData};
+
+#[derive(Eq, PartialEq, Hash, Copy, Clone)]
+enum ChargerState {
+    Patrol,
+    WindUp,
+    Charge,
+    Stunned,
+    Recover,
+    Hit,
+    Dead,
+}
+
+// Distance at which the charger notices the hero
+const SIGHT: f32 = 170.0;
+const PATROL_SPEED: f32 = 0.6;
+const CHARGE_SPEED: f32 = 4.5;
+// Frames spent dizzy after running into a wall
+const STUN_TIME: i32 = 90;
+// Frames before the next charge
+const CHARGE_COOLDOWN: i32 = 120;
+// Breath taken from the hero when rammed
+const DAMAGE: i32 = 180;
+
+/// A spearman who telegraphs, then charges across the arena until it hits a wall
+pub struct Charger {
+    id: EnemyId,
+    body: Body,
+    pub sprite: AnimatedSprite,
+    direction: f32,
+    state: ChargerState,
+    animations: HashMap<ChargerState, AnimationData>,
+
+    health: i32,
+    timer: i32,
+    cooldown: i32,
+    // Fade in at birth
+    birth: f32,
+
+    hitable: bool,
+    active: bool,
+    hited: bool,
+}
+
+impl Charger {
+    pub fn new(id: EnemyId, x: f32, y: f32, health: i32) -> Self {
+        let position = Vec2{x, y};
+        let animations = HashMap::from([
+            (ChargerState::Patrol, AnimationData{x: 0, y: 64, h: 64, w: 64, frames: 8, speed: 6, pivot_x: 0, pivot_y: 0}),
+            (ChargerState::WindUp, AnimationData{x: 0, y: 128, h: 64, w: 64, frames: 9, speed: 6, pivot_x: 0, pivot_y: 0}),
+            (ChargerState::Charge, AnimationData{x: 576, y: 128, h: 64, w: 64, frames: 4, speed: 4, pivot_x: 0, pivot_y: 0}),
+            (ChargerState::Stunned, AnimationData{x: 0, y: 0, h: 64, w: 64, frames: 8, speed: 12, pivot_x: 0, pivot_y: 0}),
+            (ChargerState::Recover, AnimationData{x: 1024, y: 128, h: 64, w: 64, frames: 4, speed: 6, pivot_x: 0, pivot_y: 0}),
+            (ChargerState::Hit, AnimationData{x: 0, y: 192, h: 64, w: 64, frames: 5, speed: 4, pivot_x: 0, pivot_y: 0}),
+            (ChargerState::Dead, AnimationData{x: 0, y: 256, h: 64, w: 64, frames: 8, speed: 8, pivot_x: 0, pivot_y: 0}),
+        ]);
+
+        let state = ChargerState::Patrol;
+        let mut sprite = AnimatedSprite::new(animations.get(&state).expect("No animation in library"));
+        sprite.set_position_to(position);
+        sprite.set_transparency(0.0);
+
+        Self {
+            id,
+            body: Body::new(position, Rect { x: 28.0, y: 24.0, w: 9.0, h: 24.0 }, 1.0, Vec2 { x: 0.8, y: 1.0 }),
+            sprite,
+            direction: if gen_range(0, 2) == 0 {-1.0} else {1.0},
+            state,
+            animations,
+
+            health,
+            timer: 0,
+            cooldown: CHARGE_COOLDOWN,
+            birth: 0.0,
+
+            hitable: false,
+            active: true,
+            hited: false,
+        }
+    }
+
+    fn state_manager(&mut self, hero_pos: Vec2) {
+        let previous_state = self.state;
+        let to_hero = hero_pos - self.body.position;
+
+        match self.state {
+            ChargerState::Patrol => {
+                self.body.velocity.x = self.direction * PATROL_SPEED;
+                if self.hitable && self.cooldown == 0 && to_hero.x.abs() < SIGHT && to_hero.y.abs() < 30.0 {
+                    self.direction = to_hero.x.signum();
+                    self.state = ChargerState::WindUp;
+                }
+            },
+            ChargerState::WindUp => {
+                // The telegraph: stand still, spear drawn back
+                self.body.velocity.x = 0.0;
+                if self.sprite.is_animation_ended() {
+                    self.state = ChargerState::Charge;
+                }
+            },
+            ChargerState::Charge => {
+                self.body.velocity.x = self.direction * CHARGE_SPEED;
+            },
+            ChargerState::Stunned => {
+                self.timer -= 1;
+                if self.timer <= 0 {
+                    self.state = ChargerState::Recover;
+                }
+            },
+            ChargerState::Recover => {
+                if self.sprite.is_animation_ended() {
+                    self.state = ChargerState::Patrol;
+                    self.direction = -self.direction;
+                    self.cooldown = CHARGE_COOLDOWN;
+                }
+            },
+            ChargerState::Hit => {
+                if self.sprite.is_animation_ended() {
+                    self.state = ChargerState::Patrol;
+
+                    if self.health <= 0 {
+                        self.state = ChargerState::Dead;
+                        self.hitable = false;
+                    }
+                }
+            },
+            ChargerState::Dead => {
+                self.hitable = false;
+                self.body.velocity.x = 0.0;
+                if self.sprite.is_animation_ended() {
+                    self.active = false;
+                }
+            },
+        }
+
+        if self.hited {
+            self.state = ChargerState::Hit;
+            self.body.velocity.x = 0.0;
+            self.hited = false;
+        }
+
+        if previous_state != self.state {
+            self.set_animation();
+        }
+    }
+
+    fn set_animation(&mut self) {
+        self.sprite.set_animation(self.animations.get(&self.state).expect("No animation"));
+        self.sprite.play();
+    }
+
+    pub fn get_collision_box(&self, dx: f32, dy: f32) -> Rect {
+        self.body.get_collision_box(dx, dy)
+    }
+
+}
+
+impl Enemy for Charger {
+    fn texture(&self) -> TextureName {
+        TextureName::Charger
+    }
+
+    fn id(&self) -> EnemyId {
+        self.id
+    }
+
+    fn update(&mut self, surroundings: &Surroundings) -> Vec<EnemyEvent> {
+        let Surroundings { hero_pos, colliders, .. } = *surroundings;
+        if self.birth < 1.0 {
+            self.birth += 0.03;
+            self.sprite.set_transparency(self.birth.min(1.0));
+            self.hitable = self.birth >= 1.0;
+        }
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+        }
+
+        self.state_manager(hero_pos);
+
+        let movement = self.body.step(colliders);
+        if self.state == ChargerState::Charge && movement.wall_normal().is_some() {
+            // Run into the wall: stunned and open to attacks
+            self.state = ChargerState::Stunned;
+            self.timer = STUN_TIME;
+            self.set_animation();
+        }
+        else if self.state == ChargerState::Patrol {
+            if let Some(normal) = movement.wall_normal() {
+                self.direction = normal;
+            }
+        }
+
+        self.sprite.flip_x = self.direction < 0.0;
+        self.sprite.set_position_to(self.body.position);
+        Vec::new()
+    }
+
+    fn position(&self) -> Vec2 {
+        self.body.position
+    }
+
+    fn hit(&mut self, hit: Hit) {
+        self.hited = true;
+        self.health -= hit.damage;
+    }
+
+    fn is_hitable(&self) -> bool {
+        self.hitable
+    }
+
+    /// Bumping into the charger hurts, except when it is dizzy or dying
+    fn is_harmful(&self) -> bool {
+        self.birth >= 1.0 && !matches!(self.state, ChargerState::Stunned | ChargerState::Dead)
+    }
+
+    fn stun(&mut self, frames: i32) {
+        if matches!(self.state, ChargerState::Patrol | ChargerState::WindUp | ChargerState::Charge) {
+            self.state = ChargerState::Stunned;
+            self.timer = frames;
+            self.set_animation();
+        }
+    }
+
+    fn get_damage(&self) -> i32 {
+        DAMAGE
+    }
+
+    fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn get_hurtboxes(&self) -> Vec<Rect> {
+        vec![self.get_collision_box(0.0, 0.0)]
+    }
+
+    fn sprite_mut(&mut self) -> &mut AnimatedSprite {
+        &mut self.sprite
+    }
+}
@@ -0,0 +1,251 @@
+use macroquad::prelude::*;
+
+// Tolerance used to decide if two boxes are touching
+const EPSILON: f32 = 0.01;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ColliderKind {
+    /// Blocks from every side
+    Solid,
+    /// Only blocks a body landing on its top side
+    OneWay,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Collider {
+    pub rect: Rect,
+    pub kind: ColliderKind,
+}
+
+impl Collider {
+    pub fn solid(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { rect: Rect { x, y, w, h }, kind: ColliderKind::Solid }
+    }
+
+    pub fn one_way(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { rect: Rect { x, y, w, h }, kind: ColliderKind::OneWay }
+    }
+}
+
+/// A surface touched during a move, with the normal pointing out of the collider
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Contact {
+    pub normal: Vec2,
+    pub kind: ColliderKind,
+}
+
+#[derive(Default, Debug)]
+pub struct Movement {
+    /// The distance really travelled by the box
+    pub delta: Vec2,
+    pub contacts: Vec<Contact>,
+    /// Standing on a solid or a one-way platform at the end of the move
+    pub grounded: bool,
+}
+
+impl Movement {
+    /// Horizontal normal of the wall hit during the move, if any
+    pub fn wall_normal(&self) -> Option<f32> {
+        self.contacts.iter().find(|c| c.normal.x != 0.0).map(|c| c.normal.x)
+    }
+
+    pub fn hit_ceiling(&self) -> bool {
+        self.contacts.iter().any(|c| c.normal.y > 0.0)
+    }
+}
+
+/// Move a box by `velocity` against the level colliders.
+/// The move is swept along x then along y, so the box stops flush against
+/// the surfaces it meets instead of stopping short of them.
+pub fn move_and_collide(body: Rect, velocity: Vec2, colliders: &[Collider], drop_through: bool) -> Movement {
+    let mut movement = Movement::default();
+
+    let dx = sweep_x(body, velocity.x, colliders, &mut movement.contacts);
+    let moved = body.offset(Vec2 { x: dx, y: 0.0 });
+    let dy = sweep_y(moved, velocity.y, colliders, drop_through, &mut movement.contacts);
+
+    movement.delta = Vec2 { x: dx, y: dy };
+    movement.grounded = is_grounded(moved.offset(Vec2 { x: 0.0, y: dy }), colliders, drop_through);
+    movement
+}
+
+/// Check if the box rests on the top of a collider
+pub fn is_grounded(body: Rect, colliders: &[Collider], drop_through: bool) -> bool {
+    colliders.iter().any(|c| {
+        if c.kind == ColliderKind::OneWay && drop_through {
+            return false;
+        }
+        overlaps_on_x(&body, &c.rect) && (c.rect.top() - body.bottom()).abs() <= EPSILON
+    })
+}
+
+fn sweep_x(body: Rect, dx: f32, colliders: &[Collider], contacts: &mut Vec<Contact>) -> f32 {
+    let mut allowed = dx;
+    let mut contact = None;
+    for c in colliders.iter().filter(|c| c.kind == ColliderKind::Solid) {
+        if !overlaps_on_y(&body, &c.rect) {
+            continue;
+        }
+        if dx > 0.0 && body.right() <= c.rect.left() + EPSILON {
+            let gap = (c.rect.left() - body.right()).max(0.0);
+            if gap <= allowed {
+                allowed = gap;
+                contact = Some(Contact { normal: Vec2 { x: -1.0, y: 0.0 }, kind: c.kind });
+            }
+        }
+        else if dx < 0.0 && body.left() >= c.rect.right() - EPSILON {
+            let gap = (c.rect.right() - body.left()).min(0.0);
+            if gap >= allowed {
+                allowed = gap;
+                contact = Some(Contact { normal: Vec2 { x: 1.0, y: 0.0 }, kind: c.kind });
+            }
+        }
+    }
+    contacts.extend(contact);
+    allowed
+}
+
+fn sweep_y(body: Rect, dy: f32, colliders: &[Collider], drop_through: bool, contacts: &mut Vec<Contact>) -> f32 {
+    let mut allowed = dy;
+    let mut contact = None;
+    for c in colliders.iter() {
+        if !overlaps_on_x(&body, &c.rect) {
+            continue;
+        }
+        if dy > 0.0 && body.bottom() <= c.rect.top() + EPSILON {
+            if c.kind == ColliderKind::OneWay && drop_through {
+                continue;
+            }
+            let gap = (c.rect.top() - body.bottom()).max(0.0);
+            if gap <= allowed {
+                allowed = gap;
+                contact = Some(Contact { normal: Vec2 { x: 0.0, y: -1.0 }, kind: c.kind });
+            }
+        }
+        else if dy < 0.0 && c.kind == ColliderKind::Solid && body.top() >= c.rect.bottom() - EPSILON {
+            let gap = (c.rect.bottom() - body.top()).min(0.0);
+            if gap >= allowed {
+                allowed = gap;
+                contact = Some(Contact { normal: Vec2 { x: 0.0, y: 1.0 }, kind: c.kind });
+            }
+        }
+    }
+    contacts.extend(contact);
+    allowed
+}
+
+/// Mirror a box given on a sprite `width` pixels wide, for the sprite flipped horizontally
+pub fn mirror_x(rect: Rect, width: f32) -> Rect {
+    Rect { x: width - rect.x - rect.w, ..rect }
+}
+
+// Strict overlap: boxes that only share an edge don't count
+fn overlaps_on_x(a: &Rect, b: &Rect) -> bool {
+    a.left() < b.right() - EPSILON && a.right() > b.left() + EPSILON
+}
+
+fn overlaps_on_y(a: &Rect, b: &Rect) -> bool {
+    a.top() < b.bottom() - EPSILON && a.bottom() > b.top() + EPSILON
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level() -> Vec<Collider> {
+        vec![
+            Collider::solid(0.0, 100.0, 200.0, 16.0),
+            Collider::solid(-16.0, 0.0, 16.0, 116.0),
+            Collider::solid(200.0, 0.0, 16.0, 116.0),
+            Collider::one_way(50.0, 60.0, 40.0, 4.0),
+        ]
+    }
+
+    #[test]
+    fn falling_box_snaps_to_the_ground() {
+        let body = Rect::new(10.0, 78.0, 10.0, 20.0);
+        let movement = move_and_collide(body, Vec2::new(0.0, 7.5), &level(), false);
+        assert_eq!(movement.delta.y, 2.0);
+        assert!(movement.grounded);
+        assert_eq!(movement.contacts[0].normal, Vec2::new(0.0, -1.0));
+    }
+
+    #[test]
+    fn fast_box_does_not_tunnel_through_a_thin_platform() {
+        let body = Rect::new(60.0, 30.0, 10.0, 20.0);
+        let movement = move_and_collide(body, Vec2::new(0.0, 40.0), &level(), false);
+        assert_eq!(movement.delta.y, 10.0);
+        assert!(movement.grounded);
+        assert_eq!(movement.contacts[0].kind, ColliderKind::OneWay);
+    }
+
+    #[test]
+    fn one_way_platform_lets_a_jump_through() {
+        let body = Rect::new(60.0, 70.0, 10.0, 20.0);
+        let movement = move_and_collide(body, Vec2::new(0.0, -30.0), &level(), false);
+        assert_eq!(movement.delta.y, -30.0);
+        assert!(movement.contacts.is_empty());
+    }
+
+    #[test]
+    fn one_way_platform_can_be_dropped_through() {
+        let body = Rect::new(60.0, 40.0, 10.0, 20.0);
+        assert!(is_grounded(body, &level(), false));
+        let movement = move_and_collide(body, Vec2::new(0.0, 0.5), &level(), true);
+        assert_eq!(movement.delta.y, 0.5);
+        assert!(!movement.grounded);
+    }
+
+    #[test]
+    fn walking_off_a_ledge_is_not_grounded() {
+        let body = Rect::new(85.0, 40.0, 10.0, 20.0);
+        assert!(is_grounded(body, &level(), false));
+        let movement = move_and_collide(body, Vec2::new(6.0, 0.0), &level(), false);
+        assert_eq!(movement.delta.x, 6.0);
+        assert!(!movement.grounded);
+    }
+
+    #[test]
+    fn wall_stops_the_box_flush() {
+        let body = Rect::new(185.0, 80.0, 10.0, 20.0);
+        let movement = move_and_collide(body, Vec2::new(8.0, 0.0), &level(), false);
+        assert_eq!(movement.delta.x, 5.0);
+        assert_eq!(movement.wall_normal(), Some(-1.0));
+        assert!(movement.grounded);
+    }
+
+    #[test]
+    fn diagonal_move_into_a_corner_is_stopped_on_both_axes() {
+        let body = Rect::new(3.0, 75.0, 10.0, 20.0);
+        let movement = move_and_collide(body, Vec2::new(-8.0, 8.0), &level(), false);
+        assert_eq!(movement.delta, Vec2::new(-3.0, 5.0));
+        assert_eq!(movement.wall_normal(), Some(1.0));
+        assert!(movement.grounded);
+    }
+
+    #[test]
+    fn edge_contact_does_not_block_a_slide() {
+        // Box exactly level with the ground top must slide freely along it
+        let body = Rect::new(20.0, 80.0, 10.0, 20.0);
+        let movement = move_and_collide(body, Vec2::new(-4.0, 0.0), &level(), false);
+        assert_eq!(movement.delta.x, -4.0);
+        assert!(movement.wall_normal().is_none());
+    }
+
+    #[test]
+    fn ceiling_stops_a_jump() {
+        let colliders = vec![Collider::solid(0.0, 0.0, 100.0, 10.0)];
+        let body = Rect::new(10.0, 14.0, 10.0, 20.0);
+        let movement = move_and_collide(body, Vec2::new(0.0, -8.0), &colliders, false);
+        assert_eq!(movement.delta.y, -4.0);
+        assert!(movement.hit_ceiling());
+    }
+
+    #[test]
+    fn mirrored_box_keeps_its_size() {
+        let mirrored = mirror_x(Rect::new(40.0, 10.0, 16.0, 8.0), 64.0);
+        assert_eq!(mirrored, Rect::new(8.0, 10.0, 16.0, 8.0));
+        assert_eq!(mirror_x(mirrored, 64.0), Rect::new(40.0, 10.0, 16.0, 8.0));
+    }
+}
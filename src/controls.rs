@@ -6,4 +6,10 @@ pub fn get_x_axis() -> f32 {
     let right = if is_key_down(KeyCode::Right) {1.0} else {0.0};
 
     right - left
-}
\ No newline at end of file
+}
+pub fn get_y_axis() -> f32 {
+    let up = if is_key_down(KeyCode::Up) {1.0} else {0.0};
+    let down = if is_key_down(KeyCode::Down) {1.0} else {0.0};
+
+    down - up
+}
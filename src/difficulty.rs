@@ -0,0 +1,80 @@
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    LastBreath,
+}
+
+/// Everything a preset changes in a run
+pub struct DifficultySettings {
+    /// Breath budget, in seconds
+    pub life_time: i32,
+    /// Breath lost each frame while attacking, on top of the normal drain
+    pub attack_drain: i32,
+    pub ghost_health: i32,
+    /// Applied to the delays between two births
+    pub spawn_delay_scale: f32,
+    /// Speed given to the hero when bumped by an enemy
+    pub knockback: f32,
+    /// Applied to the breath taken by the enemies
+    pub damage_scale: f32,
+    /// Breath given back by a kill, in frames
+    pub kill_breath: i32,
+    /// Breath given back by a combo finisher landing on an enemy, in frames
+    pub finisher_breath: i32,
+    /// Breath given back by a perfectly timed attack landing on an enemy, in frames
+    pub beat_breath: i32,
+    /// Breath given back by a successful parry, in frames
+    pub parry_breath: i32,
+    /// Frames at the start of a dash during which nothing can touch the hero
+    pub dash_iframes: i32,
+    /// Frames between two dashes
+    pub dash_cooldown: i32,
+}
+
+const ALL: [Difficulty; 4] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard, Difficulty::LastBreath];
+
+impl Difficulty {
+    pub fn settings(&self) -> DifficultySettings {
+        match self {
+            Difficulty::Easy => DifficultySettings { life_time: 30, attack_drain: 0, ghost_health: 2, spawn_delay_scale: 1.4, knockback: 6.0, damage_scale: 0.5, kill_breath: 120, finisher_breath: 90, beat_breath: 40, parry_breath: 90, dash_iframes: 10, dash_cooldown: 30 },
+            Difficulty::Normal => DifficultySettings { life_time: 20, attack_drain: 1, ghost_health: 3, spawn_delay_scale: 1.0, knockback: 8.0, damage_scale: 1.0, kill_breath: 90, finisher_breath: 60, beat_breath: 30, parry_breath: 75, dash_iframes: 8, dash_cooldown: 40 },
+            Difficulty::Hard => DifficultySettings { life_time: 15, attack_drain: 2, ghost_health: 4, spawn_delay_scale: 0.8, knockback: 9.0, damage_scale: 1.25, kill_breath: 60, finisher_breath: 45, beat_breath: 20, parry_breath: 60, dash_iframes: 6, dash_cooldown: 50 },
+            Difficulty::LastBreath => DifficultySettings { life_time: 10, attack_drain: 2, ghost_health: 5, spawn_delay_scale: 0.6, knockback: 10.0, damage_scale: 1.5, kill_breath: 45, finisher_breath: 30, beat_breath: 15, parry_breath: 45, dash_iframes: 5, dash_cooldown: 60 },
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::LastBreath => "Last Breath",
+        }
+    }
+
+    /// Short name used in the score file
+    pub fn key(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+            Difficulty::LastBreath => "last_breath",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        ALL.iter().find(|d| d.key() == key).copied()
+    }
+
+    pub fn next(self) -> Self {
+        let i = ALL.iter().position(|d| *d == self).unwrap_or(0);
+        ALL[(i + 1) % ALL.len()]
+    }
+
+    pub fn previous(self) -> Self {
+        let i = ALL.iter().position(|d| *d == self).unwrap_or(0);
+        ALL[(i + ALL.len() - 1) % ALL.len()]
+    }
+}
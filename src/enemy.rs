@@ -0,0 +1,108 @@
+use macroquad::prelude::*;
+
+use crate::collision::Collider;
+use crate::game::TextureName;
+use crate::ghost::Neighbour;
+use crate::projectile::Projectile;
+use crate::sprite::AnimatedSprite;
+use crate::wave::EnemyKind;
+
+/// Given by the game to each enemy it spawns, never reused during a run
+pub type EnemyId = u32;
+
+/// An attack landing on an enemy
+#[derive(Copy, Clone, Debug)]
+pub struct Hit {
+    pub damage: i32,
+    /// Speed given to the enemy, pushed away from the hero
+    pub knockback: Vec2,
+    /// Frames the enemy stays in its hit reaction, at least
+    pub stun: i32,
+    /// Strong enough to send the enemy bouncing off the walls
+    pub launch: bool,
+}
+
+impl Hit {
+    /// Damage alone, without any push
+    pub fn damage(damage: i32) -> Self {
+        Self { damage, knockback: Vec2::ZERO, stun: 0, launch: false }
+    }
+}
+
+/// What an enemy can see of the world during its update
+#[derive(Copy, Clone)]
+pub struct Surroundings<'a> {
+    pub hero_pos: Vec2,
+    /// The other ghosts, for the group behaviours
+    pub neighbours: &'a [Neighbour],
+    pub colliders: &'a [Collider],
+}
+
+/// Something an enemy asks the game to do
+pub enum EnemyEvent {
+    Shoot(Projectile),
+    Summon(EnemyKind, Vec2),
+}
+
+/// Everything the game needs to fight and draw an enemy.
+/// A new enemy type needs an implementation, an `EnemyKind` to name it in
+/// the wave scripts, and its constructor in `Game::monster_incubator`
+pub trait Enemy {
+    /// Sprite sheet drawn by `draw`
+    fn texture(&self) -> TextureName;
+
+    fn id(&self) -> EnemyId;
+
+    fn update(&mut self, surroundings: &Surroundings) -> Vec<EnemyEvent>;
+
+    fn position(&self) -> Vec2;
+
+    /// Boxes where the hero can hit it
+    fn get_hurtboxes(&self) -> Vec<Rect>;
+
+    /// Attack box hurting the hero, besides the body contact
+    fn get_hit_box(&self) -> Option<Rect> {
+        None
+    }
+
+    fn hit(&mut self, hit: Hit);
+
+    fn is_hitable(&self) -> bool;
+
+    /// Touching its body bumps the hero
+    fn is_harmful(&self) -> bool;
+
+    /// Breath taken from the hero by its body or its attacks, in frames
+    fn get_damage(&self) -> i32;
+
+    fn is_active(&self) -> bool;
+
+    /// Dazed by a parry for `frames`
+    fn stun(&mut self, _frames: i32) {}
+
+    fn sprite_mut(&mut self) -> &mut AnimatedSprite;
+
+    fn draw(&mut self, texture: Texture2D) {
+        self.sprite_mut().draw_sprite(texture, Vec2::ZERO, 1.0);
+    }
+
+    /// How the others see it, for ghosts only
+    fn get_neighbour(&self) -> Option<Neighbour> {
+        None
+    }
+
+    /// Center and radius of an area attack
+    fn get_shockwave(&self) -> Option<(Vec2, f32)> {
+        None
+    }
+
+    /// Health and max health, for the enemies with their own bar
+    fn get_health_bar(&self) -> Option<(i32, i32)> {
+        None
+    }
+
+    /// Text shown over the arena
+    fn get_announcement(&self) -> Option<String> {
+        None
+    }
+}
@@ -2,21 +2,33 @@ use std::collections::HashMap;
 
 use macroquad::{prelude::*, rand::gen_range};
 
+use crate::collision::Collider;
 use crate::sound_system::{SoundList, SoundBox};
 use crate::{hero::Hero, particle::Particle};
 use crate::light::Light;
-use crate::ghost::Ghost;
+use crate::enemy::{Enemy, EnemyEvent, EnemyId, Hit, Surroundings};
+use crate::ghost::{Ghost, Neighbour};
+use crate::charger::Charger;
+use crate::spirit::Spirit;
+use crate::boss::Boss;
+use crate::projectile::{self, Projectile};
+use crate::wave::{parse_waves, EnemyKind, WaveDirector};
+use crate::score::{HighScores, ScoreEntry};
+use crate::difficulty::Difficulty;
+use crate::beat::{BeatClock, BeatGrade};
 
 mod rendering;
 
 
-#[derive(Eq, PartialEq, Hash)]
-enum TextureName{
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TextureName{
     Background,
     HealthDeco,
     HealthBar,
     Ground,
     Ghost,
+    Charger,
+    Spirit,
     ParticleOne,
     Hero,
     Light,
@@ -37,16 +49,60 @@ pub enum GameState {
     End
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum GameMode {
+    /// The scripted waves, won when the last one is cleared
+    Story,
+    /// Waves keep coming until the breath runs out
+    Endless,
+}
+
+
+// Frames an attack grade stays on screen
+const BEAT_FEEDBACK_TIME: i32 = 40;
+// Frames a parried enemy stays dazed
+const PARRY_STUN: i32 = 120;
+// Frames the action freezes on a parry
+const PARRY_HITSTOP: i32 = 8;
+// Frames of the last breath sequence, and frames left when the strike lands
+const LAST_BREATH_TIME: i32 = 90;
+const LAST_BREATH_STRIKE: i32 = 45;
+// The world moves one frame out of this many during the last breath
+const LAST_BREATH_SLOWDOWN: i32 = 3;
+// Breath spent for each damage point of the strike
+const LAST_BREATH_COST: i32 = 120;
+const LAST_BREATH_ZOOM: f32 = 0.3;
+
 pub struct Game {
     state: GameState,
+    mode: GameMode,
+    difficulty: Difficulty,
     texture_library: HashMap<TextureName, Texture2D>,
+    font: Font,
     particles: Vec<Particle>,
-    max_monsters: i32,
-    monster_timer: i32,
-    monsters: Vec<Ghost>,
-    colliders: Vec<Rect>,
+    waves: WaveDirector,
+    enemies: Vec<Box<dyn Enemy>>,
+    next_enemy_id: EnemyId,
+    projectiles: Vec<Projectile>,
+    colliders: Vec<Collider>,
     lights: [Light; 6],
     hero: Hero,
+    // Breath shown by the bar, lagging behind the real one
+    breath_trail: f32,
+    beat: BeatClock,
+    // Last attack grade and the frames it stays on screen
+    beat_feedback: Option<(BeatGrade, i32)>,
+    // Frames left with the action frozen
+    hitstop: i32,
+    // Frames left in the last breath sequence, and the damage of its strike
+    last_breath: i32,
+    last_breath_damage: i32,
+    // Wave in which the last breath was used
+    last_breath_wave: Option<usize>,
+
+    score: i32,
+    high_scores: HighScores,
+    last_rank: Option<usize>,
 
     transition_alpha: f32,
     transition: TransitionName,
@@ -78,6 +134,12 @@ impl Game {
         
         let ghost_texture = Texture2D::from_file_with_format(include_bytes!("../assets/sprites/MonsterOne.png"), None);
         ghost_texture.set_filter(FilterMode::Nearest);
+
+        let charger_texture = Texture2D::from_file_with_format(include_bytes!("../assets/sprites/EnemyOne.png"), None);
+        charger_texture.set_filter(FilterMode::Nearest);
+
+        let spirit_texture = Texture2D::from_file_with_format(include_bytes!("../assets/sprites/EnemyTwo.png"), None);
+        spirit_texture.set_filter(FilterMode::Nearest);
        
         let health_container_texture = Texture2D::from_file_with_format(include_bytes!("../assets/sprites/Health_deco.png"), None);
         health_container_texture.set_filter(FilterMode::Nearest);
@@ -95,11 +157,15 @@ impl Game {
             (TextureName::ParticleOne, particle_one_texture),
             (TextureName::Light, light_texture),
             (TextureName::Ghost, ghost_texture),
+            (TextureName::Charger, charger_texture),
+            (TextureName::Spirit, spirit_texture),
             (TextureName::HealthDeco, health_container_texture),
             (TextureName::HealthBar, health_bar_texture),
             (TextureName::Title, title_texture),
         ]);
 
+        let font = load_ttf_font_from_bytes(include_bytes!("../assets/sprites/pixelFJ8.ttf")).expect("No font");
+
         let mut particles = Vec::new();
         for _i in 0..100 {
             let part = Particle::new(gen_range(0.0, 426.0), gen_range(0.0, 100.0));
@@ -115,19 +181,17 @@ impl Game {
             Light::new(330.0, 70.0, 30.0),
         ];
 
-        let max_monsters = 5;
+        // Create empty vec for enemies
+        let enemies = Vec::new();
 
-        // Delay for the first birth
-        let monster_timer = 5;
-        // Create empty vec for monster
-        let monsters = Vec::new();
 
-
-        // Level collider (Ground, left and right wall)
+        // Level collider (Ground, left and right wall, platforms)
         let colliders = vec![
-            Rect{x: 0.0, y: 101.0, w: 426.0, h: 16.0},      // Ground
-            Rect{x: -16.0, y: 0.0, w: 16.0, h: 112.0},      // Left border
-            Rect{x: 426.0, y: 0.0, w: 16.0, h: 112.0},      // Right border
+            Collider::solid(0.0, 101.0, 426.0, 16.0),       // Ground
+            Collider::solid(-16.0, 0.0, 16.0, 112.0),       // Left border
+            Collider::solid(426.0, 0.0, 16.0, 112.0),       // Right border
+            Collider::one_way(88.0, 76.0, 60.0, 4.0),       // Left platform
+            Collider::one_way(298.0, 76.0, 60.0, 4.0),      // Right platform
 
         ];
 
@@ -136,14 +200,29 @@ impl Game {
 
         Self {
             state,
+            mode: GameMode::Story,
+            difficulty: Difficulty::Normal,
             texture_library,
-            hero: Hero::new(0.0, 0.0, 20),
+            hero: Hero::new(0.0, 0.0, &Difficulty::Normal.settings()),
+            font,
             particles,
             lights,
-            max_monsters,
             colliders,
-            monster_timer,
-            monsters,
+            waves: story_waves(1.0),
+            enemies,
+            next_enemy_id: 0,
+            projectiles: Vec::new(),
+
+            breath_trail: 0.0,
+            beat: BeatClock::heartbeat(),
+            beat_feedback: None,
+            hitstop: 0,
+            last_breath: 0,
+            last_breath_damage: 0,
+            last_breath_wave: None,
+            score: 0,
+            high_scores: HighScores::load(),
+            last_rank: None,
 
             transition_alpha: 1.0,
             transition: TransitionName::FadeIn,
@@ -158,6 +237,30 @@ impl Game {
     }
     
     pub fn update(&mut self) {
+        // Freeze frames on a strong impact
+        if self.hitstop > 0 {
+            self.hitstop -= 1;
+            if self.hitstop == 0 {
+                self.set_sprites_paused(false);
+            }
+            return;
+        }
+
+        // Last breath: the world crawls around the strike
+        if self.last_breath > 0 {
+            self.last_breath -= 1;
+            if self.last_breath == LAST_BREATH_STRIKE {
+                for enemy in self.enemies.iter_mut().filter(|e| e.is_hitable()) {
+                    enemy.hit(Hit::damage(self.last_breath_damage));
+                }
+            }
+            let frozen = self.last_breath > 0 && self.last_breath % LAST_BREATH_SLOWDOWN != 0;
+            self.set_sprites_paused(frozen);
+            if frozen {
+                return;
+            }
+        }
+
         match self.state {
             GameState::Intro => {
                 if !self.ambiance_on {
@@ -166,13 +269,28 @@ impl Game {
                 }
                 self.update_decoration();
                 if self.transition_finished && self.transition == TransitionName::FadeOut{
+                    self.reset_game();
                     self.state = GameState::Game;
                     self.transition = TransitionName::FadeIn;
                     self.ambiance_on = false;
                     self.sound_bank.stop(SoundList::IntroSound);
                 }
-                if is_key_pressed(KeyCode::Space) {
-                    self.transition = TransitionName::FadeOut;
+                if self.transition != TransitionName::FadeOut {
+                    if is_key_pressed(KeyCode::Up) || is_key_pressed(KeyCode::Down) {
+                        self.mode = match self.mode {
+                            GameMode::Story => GameMode::Endless,
+                            GameMode::Endless => GameMode::Story,
+                        };
+                    }
+                    if is_key_pressed(KeyCode::Right) {
+                        self.difficulty = self.difficulty.next();
+                    }
+                    if is_key_pressed(KeyCode::Left) {
+                        self.difficulty = self.difficulty.previous();
+                    }
+                    if is_key_pressed(KeyCode::Space) {
+                        self.transition = TransitionName::FadeOut;
+                    }
                 }
 
 
@@ -183,22 +301,110 @@ impl Game {
                     self.ambiance_on = true;
                     self.sound_bank.play(SoundList::IntroSound);
                     self.sound_bank.play(SoundList::Beat);
+                    self.beat.start();
                 }
 
-                self.monster_timer -= 1;
-                if self.max_monsters > 0 && self.monster_timer == 0{
-                    self.monster_incubator();
-                    self.max_monsters -= 1;
-                    self.monster_timer = 20 + gen_range(30, 60);
+                if let Some((kind, position)) = self.waves.update(self.enemy_count()) {
+                    self.monster_incubator(kind, position);
+                }
+                for ability in self.waves.unlocks() {
+                    self.hero.unlock(ability);
                 }
                 // Clean the monster list and remove all dead monster
-                self.monsters.retain(|m| m.is_active());
+                let alive = self.enemy_count();
+                let boss_alive = self.boss().is_some();
+                self.enemies.retain(|e| e.is_active());
+                if boss_alive && self.boss().is_none() {
+                    self.sound_bank.stop(SoundList::BossAmbiance);
+                }
+                self.projectiles.retain(|p| p.is_active());
+                let kills = (alive - self.enemy_count()) as i32;
+                if kills > 0 {
+                    let wave = self.waves.current_wave().map_or(1, |(number, _)| number) as i32;
+                    self.score += 100 * wave * kills;
+                    self.hero.restore_breath(self.difficulty.settings().kill_breath * kills);
+                }
 
 
-                self.hero.update(&mut self.monsters, &self.colliders, &self.sound_bank);
+                // Be in sync: attacks on the heartbeat hit harder
+                self.hero.grade_attack(&self.beat);
+                if let Some((grade, timer)) = self.beat_feedback {
+                    self.beat_feedback = if timer > 1 {Some((grade, timer - 1))} else {None};
+                }
 
-                for monster in self.monsters.iter_mut() {
-                    monster.update(self.hero.position);
+                // Spend half of the breath in a strike on every enemy, once per wave
+                let wave = self.waves.current_wave().map(|(number, _)| number);
+                if is_key_pressed(KeyCode::Z) && self.last_breath == 0 && wave != self.last_breath_wave {
+                    if let Some(spent) = self.hero.last_breath() {
+                        self.last_breath = LAST_BREATH_TIME;
+                        self.last_breath_damage = (spent / LAST_BREATH_COST).max(2);
+                        self.last_breath_wave = wave;
+                        self.sound_bank.play(SoundList::LastBreath);
+                    }
+                }
+
+                // A parry catches bodies and attacks alike
+                if let Some(parry_box) = self.hero.get_parry_box() {
+                    let mut parried = false;
+                    for enemy in self.enemies.iter_mut().filter(|e| e.is_harmful() || e.get_hit_box().is_some()) {
+                        let caught = enemy.get_hurtboxes().iter().any(|b| b.overlaps(&parry_box))
+                            || enemy.get_hit_box().is_some_and(|b| b.overlaps(&parry_box));
+                        if caught {
+                            enemy.stun(PARRY_STUN);
+                            parried = true;
+                        }
+                    }
+                    if parried {
+                        self.hero.parry();
+                        self.sound_bank.play(SoundList::Sword2);
+                        self.hitstop = PARRY_HITSTOP;
+                        self.set_sprites_paused(true);
+                    }
+                }
+
+                // Hero attacks and body contacts, one hurtbox hit per enemy and frame
+                for enemy in self.enemies.iter_mut() {
+                    for hurtbox in enemy.get_hurtboxes() {
+                        if let Some((hit, grade)) = self.hero.check_enemy(enemy.id(), hurtbox, enemy.position(), enemy.is_hitable(), enemy.is_harmful().then(|| enemy.get_damage())) {
+                            enemy.hit(hit);
+                            // Only the hits landed on the beat score
+                            if let Some(grade) = grade {
+                                self.score += grade.score();
+                                self.beat_feedback = Some((grade, BEAT_FEEDBACK_TIME));
+                            }
+                            break;
+                        }
+                    }
+                    // Enemy attacks
+                    if let Some(hbox) = enemy.get_hit_box() {
+                        if hbox.overlaps(&self.hero.get_hurtbox()) {
+                            self.hero.take_hit(enemy.position(), enemy.get_damage());
+                        }
+                    }
+                }
+                self.check_projectiles();
+
+                self.hero.update(&self.colliders, &self.sound_bank);
+                self.update_breath_trail();
+
+                let neighbours: Vec<Option<Neighbour>> = self.enemies.iter().map(|e| e.get_neighbour()).collect();
+                let mut events = Vec::new();
+                for (i, enemy) in self.enemies.iter_mut().enumerate() {
+                    // Every ghost but this one
+                    let others: Vec<Neighbour> = neighbours.iter().enumerate()
+                        .filter_map(|(j, n)| if i == j {None} else {*n})
+                        .collect();
+                    let surroundings = Surroundings { hero_pos: self.hero.position(), neighbours: &others, colliders: &self.colliders };
+                    events.extend(enemy.update(&surroundings));
+                }
+                for event in events {
+                    match event {
+                        EnemyEvent::Shoot(projectile) => self.projectiles.push(projectile),
+                        EnemyEvent::Summon(kind, position) => self.monster_incubator(kind, position),
+                    }
+                }
+                for projectile in self.projectiles.iter_mut() {
+                    projectile.update(&self.colliders);
                 }
 
                 self.update_decoration();
@@ -207,33 +413,31 @@ impl Game {
                 if self.hero.is_dead() {
                     self.state = GameState::End;
                     self.sound_bank.stop(SoundList::Beat);
+                    self.sound_bank.stop(SoundList::BossAmbiance);
+                    self.record_score();
                 }
-                else if self.max_monsters == 0 && self.monsters.len() == 0{
+                else if self.waves.is_finished() && self.enemy_count() == 0 {
                     self.state = GameState::Win;
+                    // Remaining breath is a bonus
+                    self.score += self.hero.get_health() / 6;
+                    self.record_score();
 
                 }
 
             },
             GameState::End => {
                 self.update_decoration();
+                // Back to the title screen, to pick the mode and difficulty again
                 if is_key_pressed(KeyCode::Space) {
-                    self.reset_game();
-                    self.state = GameState::Game;
-
-                    self.sound_bank.stop(SoundList::IntroSound);
-                    self.ambiance_on = false;
+                    self.state = GameState::Intro;
                 }
             },
             GameState::Win => {
 
                 self.update_decoration();
                 if is_key_pressed(KeyCode::Space) {
-                    self.reset_game();
-                    self.state = GameState::Game;
-
-                    self.sound_bank.stop(SoundList::IntroSound);
+                    self.state = GameState::Intro;
                     self.sound_bank.stop(SoundList::Beat);
-                    self.ambiance_on = false;
                 }
             }
         }
@@ -262,6 +466,21 @@ impl Game {
         }
     }
 
+    fn set_sprites_paused(&mut self, paused: bool) {
+        let sprites = self.enemies.iter_mut().map(|e| e.sprite_mut()).chain(std::iter::once(&mut self.hero.sprite));
+        for sprite in sprites {
+            if paused {sprite.pause()} else {sprite.resume()}
+        }
+    }
+
+    /// The trail catches up with the breath, slowly enough to see what was won or lost
+    fn update_breath_trail(&mut self) {
+        let health = self.hero.get_health() as f32;
+        let gap = health - self.breath_trail;
+        let speed = (0.08 * gap.abs()).max(4.0);
+        self.breath_trail += gap.clamp(-speed, speed);
+    }
+
     fn update_decoration(&mut self) {
         for part in self.particles.iter_mut() {
             part.update();
@@ -284,18 +503,32 @@ impl Game {
                 self.render_title_screen(self.state);
             },
             GameState::Game => {
+                self.set_last_breath_camera();
+
                 // The hero and thes monsters
-                for monster in self.monsters.iter_mut() {
-                    let texture = self.texture_library.get(&TextureName::Ghost).expect("No texture in library").clone();
-                    monster.sprite.draw_sprite(texture, Vec2::ZERO, 1.0);
+                for enemy in self.enemies.iter_mut() {
+                    let texture = *self.texture_library.get(&enemy.texture()).expect("No texture in library");
+                    enemy.draw(texture);
                 }
+                self.render_shockwaves();
+                self.render_projectiles();
 
+                self.render_charge();
+                self.hero.draw_afterimages(self.get_texture(TextureName::Hero));
                 self.hero.sprite.draw_sprite(self.get_texture(TextureName::Hero), Vec2::ZERO, 1.0);
 
                 self.render_ground_mask();
+                self.render_platforms();
                 self.render_particles();
+                self.render_last_breath();
+
+                self.set_camera_view();
                 self.render_letterbox_mask();
                 self.render_health_bar();
+                self.render_dash_cooldown();
+                self.render_boss_health_bar();
+                self.render_beat();
+                self.render_wave_info();
             },
             GameState::End => {
 
@@ -324,16 +557,73 @@ impl Game {
     }
     
     fn reset_game(&mut self) {
-        self.max_monsters = 5;
-        self.monsters = Vec::new();
-        self.monster_timer = 5;
-        self.hero = Hero::new(0.0, 0.0, 20);
+        let settings = self.difficulty.settings();
+        self.waves = match self.mode {
+            GameMode::Story => story_waves(settings.spawn_delay_scale),
+            GameMode::Endless => WaveDirector::endless(settings.spawn_delay_scale),
+        };
+        self.score = 0;
+        self.last_rank = None;
+        self.enemies = Vec::new();
+        self.sound_bank.stop(SoundList::BossAmbiance);
+        self.projectiles = Vec::new();
+        self.hero = Hero::new(0.0, 0.0, &settings);
+        self.breath_trail = self.hero.get_max_health() as f32;
+        self.beat_feedback = None;
+        self.hitstop = 0;
+        self.last_breath = 0;
+        self.last_breath_wave = None;
         self.state = GameState::Intro;
     }
 
-    fn monster_incubator(&mut self) {
-        let m = Ghost::new(gen_range(50.0, 380.0), 52.0);
-        self.monsters.push(m);
+    fn record_score(&mut self) {
+        let wave = self.waves.current_wave().map_or(self.waves.wave_count().unwrap_or(0), |(number, _)| number);
+        self.last_rank = self.high_scores.record(self.mode, ScoreEntry { points: self.score, wave, difficulty: self.difficulty });
+    }
+
+    fn monster_incubator(&mut self, kind: EnemyKind, position: Vec2) {
+        let health = self.difficulty.settings().ghost_health + self.waves.health_bonus();
+        let id = self.next_enemy_id;
+        self.next_enemy_id += 1;
+        let enemy: Box<dyn Enemy> = match kind {
+            EnemyKind::Ghost => Box::new(Ghost::new(id, position.x, position.y, health)),
+            EnemyKind::Charger => Box::new(Charger::new(id, position.x, position.y, health + 1)),
+            EnemyKind::Spirit => Box::new(Spirit::new(id, position.x, position.y, health - 1)),
+            EnemyKind::Boss => {
+                self.sound_bank.stop(SoundList::IntroSound);
+                self.sound_bank.play(SoundList::BossAmbiance);
+                Box::new(Boss::new(id, position.x, position.y, 8 * health))
+            },
+        };
+        self.enemies.push(enemy);
+    }
+
+    fn enemy_count(&self) -> usize {
+        self.enemies.len()
+    }
+
+    /// The enemy with its own health bar, if any
+    fn boss(&self) -> Option<&dyn Enemy> {
+        self.enemies.iter().find(|e| e.get_health_bar().is_some()).map(|e| e.as_ref())
+    }
+
+    /// Projectiles are dodged by dashing, cut by the double slash, or hit the hero
+    fn check_projectiles(&mut self) {
+        let dodging = self.hero.is_dodging();
+        let deflect_box = self.hero.get_deflect_box();
+        let hero_box = self.hero.get_hurtbox();
+
+        for projectile in self.projectiles.iter_mut() {
+            let p_box = projectile.get_collision_box();
+            if deflect_box.is_some_and(|b| b.overlaps(&p_box)) {
+                projectile.destroy();
+                self.sound_bank.play(SoundList::Sword2);
+            }
+            else if !dodging && hero_box.overlaps(&p_box) {
+                projectile.destroy();
+                self.hero.take_hit(projectile.position - Vec2 { x: 32.0, y: 38.0 }, projectile::DAMAGE);
+            }
+        }
     }
 
     /// An ugly experimental empiric camera setting function
@@ -343,6 +633,24 @@ impl Game {
         let camera = Camera2D::from_display_rect(Rect{x: 0.0, y: -0.5 * (h - 112.0), w: 426.0, h});
         set_camera(&camera);
     }
+
+    /// Zoom on the hero during the last breath, back to normal once it is over
+    fn set_last_breath_camera(&mut self) {
+        if self.last_breath == 0 {
+            return;
+        }
+        let ratio =  screen_width() / 1278.;
+        let h = 240.0 * screen_height() / 720. / ratio;
+        let progress = 1.0 - self.last_breath as f32 / LAST_BREATH_TIME as f32;
+        let zoom = LAST_BREATH_ZOOM * (std::f32::consts::PI * progress).sin();
+
+        let screen_center = Vec2 { x: 213.0, y: 56.0 };
+        let hero_center = self.hero.position() + Vec2 { x: 32.0, y: 38.0 };
+        let center = screen_center.lerp(hero_center, zoom / LAST_BREATH_ZOOM);
+        let (w, h) = (426.0 / (1.0 + zoom), h / (1.0 + zoom));
+        let camera = Camera2D::from_display_rect(Rect{x: center.x - 0.5 * w, y: center.y - 0.5 * h, w, h});
+        set_camera(&camera);
+    }
  
     //fn debug_info(&mut self) {
     //    // Reset game
@@ -371,4 +679,9 @@ impl Game {
     //    //draw_text(&format!("position: {} / {}", self.hero.position.x, self.hero.position.y), 16.0, 32.0, 24.0, RED);
 
     //} 
-}
\ No newline at end of file
+}
+
+fn story_waves(delay_scale: f32) -> WaveDirector {
+    let waves = parse_waves(include_str!("../assets/data/waves.txt")).expect("Invalid wave script");
+    WaveDirector::new(waves, delay_scale)
+}
@@ -1,8 +1,12 @@
 use macroquad::prelude::*;
 use super::Game;
+use crate::collision::ColliderKind;
 
 use super::GameState;
+use super::GameMode;
 use super::TextureName;
+use super::{BEAT_FEEDBACK_TIME, LAST_BREATH_STRIKE, LAST_BREATH_TIME};
+use crate::beat::BeatGrade;
 
 
 impl Game {
@@ -42,6 +46,17 @@ impl Game {
                     draw_texture_ex(self.get_texture(TextureName::Title), 149.0, 80.0, color, press_params);
                 };
 
+                // Mode selection and its best score
+                let mode = match self.mode {
+                    GameMode::Story => "- Story -",
+                    GameMode::Endless => "- Endless -",
+                };
+                self.draw_hud_text_centered(mode, 130.0, Color::from_rgba(255, 236, 214, 255));
+                self.draw_hud_text_centered(&format!("< {} >", self.difficulty.name()), 140.0, Color::from_rgba(255, 170, 94, 255));
+                if let Some(best) = self.high_scores.table(self.mode).first() {
+                    self.draw_hud_text_centered(&format!("Best {}", best.points), 152.0, Color::from_rgba(141, 105, 122, 255));
+                }
+
 
             },
             GameState::Win => {
@@ -57,6 +72,7 @@ impl Game {
 
                 let color = Color::new(1.0, 1.0, 1.0, 0.9 + 0.1 * ((get_time() * 6.0)as f32).cos());
                 draw_texture_ex(self.get_texture(TextureName::Title), 149.0, 40.0, color, title_params);
+                self.render_score_table();
                 
             },
             GameState::End => {
@@ -71,6 +87,7 @@ impl Game {
 
                 let color = Color::new(1.0, 1.0, 1.0, 0.9 + 0.1 * ((get_time() * 6.0)as f32).cos());
                 draw_texture_ex(self.get_texture(TextureName::Title), 149.0, 40.0, color, title_params);
+                self.render_score_table();
                 
             },
             _  => {}
@@ -85,11 +102,15 @@ impl Game {
         draw_texture(self.get_texture(TextureName::HealthDeco), 81.0, -48.0, WHITE);
         // Health bar
 
-        let width = 240.0 * self.hero.get_health() as f32 / 1200.0;
+        let max_health = self.hero.get_max_health() as f32;
+        let width = 240.0 * self.hero.get_health() as f32 / max_health;
+        let trail = 240.0 * self.breath_trail / max_health;
 
+        // The bar stops at the trail while breath comes back
+        let bar = width.min(trail);
         let health_params = DrawTextureParams {
-            dest_size: Some(Vec2{x: width, y: 8.0}),
-            source: Some(Rect::new(0.0, 0.0, width, 8.0)),
+            dest_size: Some(Vec2{x: bar, y: 8.0}),
+            source: Some(Rect::new(0.0, 0.0, bar, 8.0)),
             rotation: 0.0,
             flip_x: false,
             flip_y: false,
@@ -97,6 +118,167 @@ impl Game {
         };
         let color = Color::new(1.0, 1.0, 1.0, 0.9 + 0.1 * ((get_time() * 4.0)as f32).cos());
         draw_texture_ex(self.get_texture(TextureName::HealthBar), 85.0, -36.0, color, health_params);
+
+        // Gain or loss trail between the breath and what the bar showed
+        let (from, to, trail_color) = if trail > width {
+            (width, trail, Color::from_rgba(255, 236, 214, 160))
+        }
+        else {
+            (trail, width, Color::from_rgba(255, 212, 163, 255))
+        };
+        if to - from >= 1.0 {
+            draw_rectangle(85.0 + from, -36.0, to - from, 8.0, trail_color);
+        }
+    }
+
+    /// The screen darkens while the last breath is drawn in, then flashes on the strike
+    pub fn render_last_breath(&mut self) {
+        if self.last_breath == 0 {
+            return;
+        }
+        let color = if self.last_breath > LAST_BREATH_STRIKE {
+            let t = (LAST_BREATH_TIME - self.last_breath) as f32 / (LAST_BREATH_TIME - LAST_BREATH_STRIKE) as f32;
+            Color::new(0.05, 0.17, 0.27, 0.6 * t)
+        }
+        else {
+            let t = self.last_breath as f32 / LAST_BREATH_STRIKE as f32;
+            Color::new(1.0, 0.93, 0.84, t * t)
+        };
+        draw_rectangle(-64.0, -128.0, 554.0, 368.0, color);
+    }
+
+    /// Aura around the hero while charging the heavy attack
+    pub fn render_charge(&mut self) {
+        if let Some(level) = self.hero.get_charge_level() {
+            let position = self.hero.position();
+            let pulse = 0.5 + 0.5 * (get_time() as f32 * 8.0).sin();
+            let radius = 8.0 + 4.0 * level as f32 + pulse;
+            let color = Color::from_rgba(255, 170, 94, (30 + 25 * level) as u8);
+            draw_circle(position.x + 32.0, position.y + 38.0, radius, color);
+        }
+    }
+
+    /// A dot after the breath bar, filling up until the next dash is ready
+    pub fn render_dash_cooldown(&mut self) {
+        let cooldown = self.hero.get_dash_cooldown();
+        if cooldown > 0.0 {
+            draw_circle_lines(352.0, -32.0, 3.0, 1.0, Color::from_rgba(84, 78, 104, 255));
+            draw_circle(352.0, -32.0, 3.0 * (1.0 - cooldown), Color::from_rgba(141, 105, 122, 255));
+        }
+        else {
+            draw_circle(352.0, -32.0, 3.0, Color::from_rgba(255, 170, 94, 255));
+        }
+    }
+
+    /// Heart pulse by the breath bar, and the grade of the last attack over the hero
+    pub fn render_beat(&mut self) {
+        let pulse = (1.0 - self.beat.phase()).powi(4);
+        draw_circle(70.0, -32.0, 3.0 + 2.0 * pulse, Color::new(0.82, 0.51, 0.35, 0.5 + 0.5 * pulse));
+
+        if let Some((grade, timer)) = self.beat_feedback {
+            let color = match grade {
+                BeatGrade::Perfect => Color::from_rgba(255, 236, 214, 255),
+                BeatGrade::Good => Color::from_rgba(255, 170, 94, 255),
+                BeatGrade::Miss => Color::from_rgba(141, 105, 122, 255),
+            };
+            let t = timer as f32 / BEAT_FEEDBACK_TIME as f32;
+            let color = Color { a: t, ..color };
+            let text = grade.name();
+            let size = measure_text(text, Some(self.font), 24, 1.0 / 3.0);
+            let position = self.hero.position();
+            // Rise above the head while fading
+            self.draw_hud_text(text, position.x + 32.0 - 0.5 * size.width, position.y + 20.0 - 10.0 * (1.0 - t), color);
+        }
+    }
+
+    pub fn render_boss_health_bar(&mut self) {
+        let Some((health, max_health)) = self.boss().and_then(|b| b.get_health_bar()) else {
+            return;
+        };
+        let width = 240.0 * health as f32 / max_health as f32;
+        let announcement = self.boss().and_then(|b| b.get_announcement());
+
+        // Same bar as the hero one, in the bottom letterbox and tinted
+        draw_texture(self.get_texture(TextureName::HealthDeco), 81.0, 136.0, WHITE);
+        let health_params = DrawTextureParams {
+            dest_size: Some(Vec2{x: width, y: 8.0}),
+            source: Some(Rect::new(0.0, 0.0, width, 8.0)),
+            rotation: 0.0,
+            flip_x: false,
+            flip_y: false,
+            pivot: None
+        };
+        let color = Color::from_rgba(208, 129, 89, 255);
+        draw_texture_ex(self.get_texture(TextureName::HealthBar), 85.0, 148.0, color, health_params);
+
+        // Phase announcement
+        if let Some(text) = announcement {
+            let alpha = 0.7 + 0.3 * ((get_time() * 8.0) as f32).cos();
+            self.draw_hud_text_centered(&text, 40.0, Color::new(1.0, 0.93, 0.84, alpha));
+        }
+    }
+
+    pub fn render_wave_info(&mut self) {
+        if let Some((number, name)) = self.waves.current_wave() {
+            let text = match self.waves.wave_count() {
+                Some(count) => format!("Wave {}/{} - {}", number, count, name),
+                None => format!("Wave {} - {}", number, name),
+            };
+            self.draw_hud_text(&text, 85.0, 128.0, Color::from_rgba(255, 236, 214, 200));
+        }
+        // Above the breath bar, the bottom is kept for the boss
+        self.draw_hud_text(&format!("{}", self.score), 85.0, -52.0, Color::from_rgba(255, 170, 94, 200));
+        self.draw_hud_text(self.difficulty.name(), 280.0, -52.0, Color::from_rgba(141, 105, 122, 200));
+        if self.hero.get_combo() > 1 {
+            self.draw_hud_text_centered(&format!("{} Hits", self.hero.get_combo()), -52.0, Color::from_rgba(255, 236, 214, 220));
+        }
+    }
+
+    pub fn render_score_table(&mut self) {
+        let light = Color::from_rgba(255, 236, 214, 255);
+        let dark = Color::from_rgba(141, 105, 122, 255);
+        self.draw_hud_text_centered(&format!("Score {}", self.score), 124.0, light);
+        for (rank, entry) in self.high_scores.table(self.mode).iter().enumerate() {
+            let color = if self.last_rank == Some(rank) {light} else {dark};
+            let text = format!("{}. {:>6}  wave {}  {}", rank + 1, entry.points, entry.wave, entry.difficulty.name());
+            self.draw_hud_text_centered(&text, 134.0 + 8.0 * rank as f32, color);
+        }
+    }
+
+    pub fn draw_hud_text_centered(&self, text: &str, y: f32, color: Color) {
+        let size = measure_text(text, Some(self.font), 24, 1.0 / 3.0);
+        self.draw_hud_text(text, 0.5 * (426.0 - size.width), y, color);
+    }
+
+    pub fn draw_hud_text(&self, text: &str, x: f32, y: f32, color: Color) {
+        // Rasterized bigger then scaled down to stay sharp with the zoomed camera
+        let params = TextParams {
+            font: self.font,
+            font_size: 24,
+            font_scale: 1.0 / 3.0,
+            color,
+            ..Default::default()
+        };
+        draw_text_ex(text, x, y, params);
+    }
+
+    pub fn render_shockwaves(&mut self) {
+        for enemy in self.enemies.iter() {
+            if let Some((center, radius)) = enemy.get_shockwave() {
+                let color = Color::new(1.0, 0.93, 0.84, 1.0 - radius / 50.0);
+                draw_circle_lines(center.x, center.y, radius, 1.0, color);
+            }
+        }
+    }
+
+    pub fn render_projectiles(&mut self) {
+        let glow = Color::from_rgba(255, 170, 94, 90);
+        let core = Color::from_rgba(255, 236, 214, 255);
+        for projectile in self.projectiles.iter() {
+            let flicker = 1.0 + 0.5 * ((get_time() * 12.0) as f32).sin();
+            draw_circle(projectile.position.x, projectile.position.y, projectile.radius + flicker, glow);
+            draw_circle(projectile.position.x, projectile.position.y, projectile.radius - 1.0, core);
+        }
     }
 
     pub fn render_letterbox_mask(&mut self) {
@@ -126,6 +308,17 @@ impl Game {
 
     }
    
+    pub fn render_platforms(&mut self) {
+        // One-way platforms are not part of the level texture
+        let body_color = Color::from_rgba(32, 60, 86, 255);
+        let edge_color = Color::from_rgba(141, 105, 122, 255);
+        for collider in self.colliders.iter().filter(|c| c.kind == ColliderKind::OneWay) {
+            let r = collider.rect;
+            draw_rectangle(r.x, r.y, r.w, r.h, body_color);
+            draw_rectangle(r.x, r.y, r.w, 1.0, edge_color);
+        }
+    }
+   
     pub fn render_background(&mut self) {
         let bg_params = DrawTextureParams {
             dest_size: Some(Vec2::new(426.0, 112.0)),
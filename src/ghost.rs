@@ -2,7 +2,15 @@ use std::collections::HashMap;
 
 use macroquad::{prelude::*, rand::gen_range};
 
+use crate::enemy::{Enemy, EnemyEvent, EnemyId, Hit, Surroundings};
+use crate::game::TextureName;
+use crate::physics::Body;
 use crate::sprite::{AnimatedSprite, AnimationData};
+use behaviour::Behaviour;
+pub use behaviour::Neighbour;
+
+mod behaviour;
+mod hurtbox;
 
 #[derive(Eq, PartialEq, Hash, Copy, Clone)]
 enum MonsterState {
@@ -10,41 +18,70 @@ enum MonsterState {
     Birth,
     Dead,
     Hit,
+    LungeWindUp,
+    Lunge,
+    ScreamWindUp,
+    Scream,
+    Stunned,
 }
 
-enum Behaviour {
-    UpDown {yo: f32, speed: f32, dt: f32},
-    StandBy,
-}
+// Attack timings, in frames
+const LUNGE_WIND_UP: i32 = 40;
+const LUNGE_ACTIVE: i32 = 16;
+const LUNGE_SPEED: f32 = 3.5;
+const SCREAM_WIND_UP: i32 = 45;
+const SCREAM_ACTIVE: i32 = 20;
+const SCREAM_RADIUS: f32 = 45.0;
+// Breath taken from the hero by a touch or an attack
+const DAMAGE: i32 = 120;
+// Part of the knockback kept each frame, on top of the body drag
+const HIT_DRAG: f32 = 0.85;
+// Part of the speed kept by a launched ghost bouncing off a wall, and the damage it takes
+const BOUNCE: f32 = 0.6;
+const BOUNCE_DAMAGE: i32 = 1;
 
 pub struct Ghost {
-    pub position: Vec2,
-    pub velocity: Vec2,
+    id: EnemyId,
+    body: Body,
     pub sprite: AnimatedSprite,
     direction: f32,
-    collision_box: Rect,
     state: MonsterState,
     animations: HashMap<MonsterState, AnimationData>,
 
     behaviour: Behaviour,
+    think_timer: i32,
+    flee_timer: i32,
 
     health: i32,
+    max_health: i32,
+
+    attack_timer: i32,
+    attack_cooldown: i32,
+    // Frames left in the hit reaction, and launched by a heavy hit
+    stun_timer: i32,
+    launched: bool,
+    lunge_dir: Vec2,
+    scream_radius: f32,
 
     hitable: bool,
     active: bool,
-    hited: bool,
 
     
 }
 
 impl Ghost {
-    pub fn new(x: f32, y: f32) -> Self {
+    pub fn new(id: EnemyId, x: f32, y: f32, health: i32) -> Self {
         let position = Vec2{x, y};
         let animations = HashMap::from([
             (MonsterState::Idle, AnimationData{x: 0, y: 0, h: 64, w: 64, frames: 5, speed: 8, pivot_x: 0, pivot_y: 0}),
             (MonsterState::Hit, AnimationData{x: 0, y: 64, h: 64, w: 64, frames: 10, speed: 4, pivot_x: 0, pivot_y: 0}),
             (MonsterState::Dead, AnimationData{x: 0, y: 128, h: 64, w: 64, frames: 10, speed: 8, pivot_x: 0, pivot_y: 0}),
             (MonsterState::Birth, AnimationData{x: 0, y: 192, h: 64, w: 64, frames: 13, speed: 8, pivot_x: 0, pivot_y: 0}),
+            (MonsterState::LungeWindUp, AnimationData{x: 0, y: 0, h: 64, w: 64, frames: 5, speed: 2, pivot_x: 0, pivot_y: 0}),
+            (MonsterState::Lunge, AnimationData{x: 0, y: 0, h: 64, w: 64, frames: 5, speed: 3, pivot_x: 0, pivot_y: 0}),
+            (MonsterState::ScreamWindUp, AnimationData{x: 576, y: 192, h: 64, w: 64, frames: 4, speed: 12, pivot_x: 0, pivot_y: 0}),
+            (MonsterState::Scream, AnimationData{x: 0, y: 64, h: 64, w: 64, frames: 10, speed: 2, pivot_x: 0, pivot_y: 0}),
+            (MonsterState::Stunned, AnimationData{x: 0, y: 0, h: 64, w: 64, frames: 5, speed: 20, pivot_x: 0, pivot_y: 0}),
 
 
         ]);
@@ -54,66 +91,36 @@ impl Ghost {
         sprite.set_position_to(position);
 
         Self {
-            position,
-            velocity: Vec2::ZERO,
+            id,
+            // Ghosts float: no gravity, and they slow down on both axes
+            body: Body::new(position, Rect { x: 25.0, y: 19.0, w: 15.0, h: 22.0 }, 0.0, Vec2 { x: 0.95, y: 0.95 }),
             state,
             animations,
             sprite,
-            collision_box: Rect { x: 25.0, y: 19.0, w: 15.0, h: 22.0 },
             direction: 0.0,
 
             behaviour: Behaviour::StandBy,
+            think_timer: 0,
+            flee_timer: 0,
+
+            health,
+            max_health: health,
 
-            health: 3,
+            attack_timer: 0,
+            attack_cooldown: gen_range(60, 180),
+            stun_timer: 0,
+            launched: false,
+            lunge_dir: Vec2::ZERO,
+            scream_radius: 0.0,
 
             hitable:false,
-            hited: false,
 
             active: true,
         }
 
     }
 
-    pub fn update(&mut self, hero_pos: Vec2) {
-
-        self.brain(hero_pos);
-
-
-        // Look in the right direction
-        if self.position.x > hero_pos.x {
-            self.direction = -1.0;
-        }
-        else if self.position.x < hero_pos.x {
-            self.direction = 1.0;
-        }
-
-        self.state_manager();
-
-        self.position += self.velocity;
-        self.sprite.set_position_to(self.position);
-    }
-
-
-    fn brain(&mut self, _hero_pos: Vec2) {
-        if self.state == MonsterState::Idle {
-            match self.behaviour {
-                Behaviour::StandBy => {
-                    if gen_range(0, 100) < 2 {
-                        self.behaviour = Behaviour::UpDown { yo: self.position.y, speed: 0.01, dt: 0.0 };
-                    }
-                },
-                Behaviour::UpDown { yo, speed, dt } => {
-                    self.position.y = yo + 15.0 * dt.sin();
-                    self.behaviour = Behaviour::UpDown { yo, speed: 0.01, dt: dt + speed };
-                }
-            }
-
-        } 
-        
-    }
-
-    fn state_manager(&mut self) {
-        let previous_state = self.state;
+    fn state_manager(&mut self, previous_state: MonsterState) {
          match self.state {
             MonsterState::Birth => {
                 if self.sprite.is_animation_ended() {
@@ -122,12 +129,62 @@ impl Ghost {
                 }
             },
             MonsterState::Idle => {},
+            MonsterState::LungeWindUp => {
+                // Draw back and flicker: the tell
+                self.body.velocity = -0.4 * self.lunge_dir;
+                self.sprite.set_transparency(if self.attack_timer % 8 < 4 {0.5} else {1.0});
+                self.attack_timer -= 1;
+                if self.attack_timer <= 0 {
+                    self.sprite.set_transparency(1.0);
+                    self.state = MonsterState::Lunge;
+                    self.attack_timer = LUNGE_ACTIVE;
+                }
+            },
+            MonsterState::Lunge => {
+                self.body.velocity = LUNGE_SPEED * self.lunge_dir;
+                self.attack_timer -= 1;
+                if self.attack_timer <= 0 {
+                    self.end_attack();
+                }
+            },
+            MonsterState::ScreamWindUp => {
+                self.body.velocity = Vec2::ZERO;
+                self.attack_timer -= 1;
+                if self.attack_timer <= 0 {
+                    self.state = MonsterState::Scream;
+                    self.attack_timer = SCREAM_ACTIVE;
+                }
+            },
+            MonsterState::Scream => {
+                self.attack_timer -= 1;
+                self.scream_radius = SCREAM_RADIUS * (1.0 - self.attack_timer as f32 / SCREAM_ACTIVE as f32);
+                if self.attack_timer <= 0 {
+                    self.scream_radius = 0.0;
+                    self.end_attack();
+                }
+            },
+            MonsterState::Stunned => {
+                // Dazed and wide open
+                self.sprite.set_transparency(if self.attack_timer % 16 < 8 {0.7} else {1.0});
+                self.attack_timer -= 1;
+                if self.attack_timer <= 0 {
+                    self.end_attack();
+                }
+            },
             MonsterState::Hit => {
-                self.hitable = false;
-                self.hited = false;
-                if self.sprite.is_animation_ended() {
+                // Pushed back and slowing down, a launched ghost flies further
+                if self.launched {
+                    self.launched = self.body.velocity.length() > 1.0;
+                }
+                else {
+                    self.body.velocity *= HIT_DRAG;
+                }
+                if self.stun_timer > 0 {
+                    self.stun_timer -= 1;
+                }
+                if self.sprite.is_animation_ended() && self.stun_timer == 0 {
                     self.state = MonsterState::Idle;
-                    self.hitable = true;
+                    self.launched = false;
 
                     if self.health <= 0 {
                         self.state = MonsterState::Dead;
@@ -150,31 +207,220 @@ impl Ghost {
             self.sprite.flip_x = true;
         }
 
-
-        //if self.health <= 0 {self.state = MonsterState::Dead}
-        if self.hited {self.state = MonsterState::Hit}
-
-
         if previous_state != self.state {
             self.sprite.set_animation(self.animations.get(&self.state).expect("No animation"));
             self.sprite.play();
         }
     }
     
+    fn end_attack(&mut self) {
+        self.state = MonsterState::Idle;
+        self.attack_timer = 0;
+        self.scream_radius = 0.0;
+        self.attack_cooldown = gen_range(120, 240);
+        self.sprite.set_transparency(1.0);
+        // The bob restarts from where the attack left the ghost
+        self.behaviour = Behaviour::StandBy;
+    }
+
     pub fn get_collision_box(&self, dx: f32, dy: f32) -> Rect {
-        Rect { x: self.position.x + self.collision_box.x + dx, y: self.position.y + self.collision_box.y + dy, w: self.collision_box.w, h: self.collision_box.h }
+        self.body.get_collision_box(dx, dy)
+    }
+}
+
+impl Enemy for Ghost {
+    fn texture(&self) -> TextureName {
+        TextureName::Ghost
+    }
+
+    fn id(&self) -> EnemyId {
+        self.id
+    }
+
+    /// The neighbours are the other ghosts, without this one
+    fn update(&mut self, surroundings: &Surroundings) -> Vec<EnemyEvent> {
+        let Surroundings { hero_pos, neighbours, colliders } = *surroundings;
+
+        let previous_state = self.state;
+        self.brain(hero_pos, neighbours);
+
+
+        // Look in the right direction
+        if self.body.position.x > hero_pos.x {
+            self.direction = -1.0;
+        }
+        else if self.body.position.x < hero_pos.x {
+            self.direction = 1.0;
+        }
+
+        self.state_manager(previous_state);
+
+        let velocity = self.body.velocity;
+        let movement = self.body.step(colliders);
+        if self.launched && !movement.contacts.is_empty() {
+            // Bounce off the level, once, and feel it
+            for contact in movement.contacts.iter() {
+                if contact.normal.x != 0.0 {
+                    self.body.velocity.x = -BOUNCE * velocity.x;
+                }
+                if contact.normal.y != 0.0 {
+                    self.body.velocity.y = -BOUNCE * velocity.y;
+                }
+            }
+            self.health -= BOUNCE_DAMAGE;
+            self.launched = false;
+        }
+        self.sprite.set_position_to(self.body.position);
+        Vec::new()
+    }
+
+    fn get_hurtboxes(&self) -> Vec<Rect> {
+        hurtbox::get_hurt_boxes(self.state, self.sprite.current_frame, self.sprite.flip_x)
+            .into_iter()
+            .map(|b| Rect { x: b.x + self.body.position.x, y: b.y + self.body.position.y, ..b })
+            .collect()
+    }
+
+    /// Box hurting the hero during the active frames of an attack
+    fn get_hit_box(&self) -> Option<Rect> {
+        match self.state {
+            MonsterState::Lunge => Some(self.get_collision_box(0.0, 0.0)),
+            MonsterState::Scream if self.scream_radius > 0.0 => {
+                let center = self.get_collision_box(0.0, 0.0).center();
+                let r = self.scream_radius;
+                Some(Rect { x: center.x - r, y: center.y - r, w: 2.0 * r, h: 2.0 * r })
+            },
+            _ => None,
+        }
+    }
+
+    /// Center and radius of the scream shockwave, while it spreads
+    fn get_shockwave(&self) -> Option<(Vec2, f32)> {
+        if self.state == MonsterState::Scream && self.scream_radius > 0.0 {
+            Some((self.get_collision_box(0.0, 0.0).center(), self.scream_radius))
+        }
+        else {
+            None
+        }
     }
 
-    pub fn hit(&mut self, value: i32) {
-        self.hited = true;
-        self.hitable = false;
-        self.health -= value;
+    fn position(&self) -> Vec2 {
+        self.body.position
     }
-    pub fn is_hitable(&self) -> bool {
+
+    /// React right away, so that nothing moves the ghost before the knockback does
+    fn hit(&mut self, hit: Hit) {
+        // A hit cancels any attack
+        if self.state != MonsterState::Hit {
+            self.end_attack();
+        }
+        self.state = MonsterState::Hit;
+        self.sprite.set_animation(self.animations.get(&self.state).expect("No animation"));
+        self.sprite.play();
+
+        self.health -= hit.damage;
+        self.body.velocity = hit.knockback;
+        self.stun_timer = hit.stun;
+        self.launched = hit.launch;
+        self.flee_timer = 120;
+        // Rethink right after the hit
+        self.think_timer = 0;
+    }
+
+    fn is_hitable(&self) -> bool {
         self.hitable
     }
 
-    pub fn is_active(&self) -> bool {
+    fn is_harmful(&self) -> bool {
+        self.hitable && !matches!(self.state, MonsterState::Stunned | MonsterState::Hit)
+    }
+
+    fn stun(&mut self, frames: i32) {
+        if self.is_harmful() {
+            self.end_attack();
+            self.state = MonsterState::Stunned;
+            self.attack_timer = frames;
+            self.sprite.set_animation(self.animations.get(&self.state).expect("No animation"));
+            self.sprite.play();
+        }
+    }
+
+    fn get_damage(&self) -> i32 {
+        DAMAGE
+    }
+
+    fn is_active(&self) -> bool {
         self.active
     }
-}
\ No newline at end of file
+
+    fn sprite_mut(&mut self) -> &mut AnimatedSprite {
+        &mut self.sprite
+    }
+
+    fn get_neighbour(&self) -> Option<Neighbour> {
+        Some((self.body.position, self.behaviour))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision::Collider;
+
+    fn heavy() -> Hit {
+        Hit { damage: 1, knockback: Vec2::new(6.0, 0.0), stun: 20, launch: true }
+    }
+
+    fn idle_ghost(x: f32) -> Ghost {
+        let mut ghost = Ghost::new(0, x, 40.0, 5);
+        ghost.state = MonsterState::Idle;
+        ghost.hitable = true;
+        ghost
+    }
+
+    fn step(ghost: &mut Ghost, colliders: &[Collider]) {
+        let surroundings = Surroundings { hero_pos: Vec2::new(0.0, 40.0), neighbours: &[], colliders };
+        ghost.update(&surroundings);
+    }
+
+    #[test]
+    fn knockback_is_not_lost_to_the_steering() {
+        let mut ghost = idle_ghost(100.0);
+        ghost.hit(heavy());
+        step(&mut ghost, &[]);
+        assert!(ghost.position().x - 100.0 > 5.0);
+    }
+
+    #[test]
+    fn knockback_cancels_a_lunge() {
+        let mut ghost = idle_ghost(100.0);
+        ghost.state = MonsterState::Lunge;
+        ghost.attack_timer = LUNGE_ACTIVE;
+        ghost.lunge_dir = Vec2::new(-1.0, 0.0);
+        ghost.hit(heavy());
+        step(&mut ghost, &[]);
+        assert!(ghost.position().x - 100.0 > 5.0);
+    }
+
+    #[test]
+    fn launched_ghost_bounces_off_a_wall() {
+        // The ghost box ends 20 pixels before the wall
+        let colliders = [Collider::solid(160.0, 0.0, 16.0, 112.0)];
+        let mut ghost = idle_ghost(100.0);
+        ghost.hit(heavy());
+        for _ in 0..6 {
+            step(&mut ghost, &colliders);
+        }
+        assert!(ghost.body.velocity.x < 0.0);
+        assert_eq!(ghost.health, 5 - 1 - BOUNCE_DAMAGE);
+    }
+    #[test]
+    fn hit_ghost_stays_hitable_but_harmless() {
+        let mut ghost = idle_ghost(100.0);
+        ghost.hit(heavy());
+        step(&mut ghost, &[]);
+        assert!(ghost.is_hitable());
+        assert!(!ghost.is_harmful());
+    }
+}
@@ -0,0 +1,215 @@
+use macroquad::{prelude::*, rand::gen_range};
+
+use crate::steering;
+use super::{Ghost, MonsterState, LUNGE_WIND_UP, SCREAM_WIND_UP};
+
+/// What a ghost does between two attacks.
+/// A new behaviour only needs a variant, a score in `score` and a move in `steer`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Behaviour {
+    StandBy,
+    UpDown {yo: f32, speed: f32, dt: f32},
+    /// Close the distance with the hero
+    Approach,
+    /// Turn around the hero at a safe distance
+    Orbit {angle: f32, dir: f32},
+    /// Run away after being hurt
+    Retreat,
+    /// Hover over the hero's head
+    CircleOverhead {angle: f32},
+    /// Fade and wait until the hero comes close
+    Ambush,
+}
+
+/// What the ghost knows about the others
+pub type Neighbour = (Vec2, Behaviour);
+
+// Frames between two decisions
+const THINK_TIME: i32 = 45;
+// Radius in which other ghosts are taken into account
+const NEIGHBOURHOOD: f32 = 100.0;
+const AMBUSH_TRIGGER: f32 = 60.0;
+
+// Arena limits for the ghost position
+const MIN_X: f32 = -10.0;
+const MAX_X: f32 = 370.0;
+const MIN_Y: f32 = -10.0;
+const MAX_Y: f32 = 60.0;
+
+// Steering tuning
+const MAX_SPEED: f32 = 1.2;
+const MAX_FORCE: f32 = 0.08;
+const SLOWING_RADIUS: f32 = 30.0;
+const SEPARATION_RADIUS: f32 = 24.0;
+const SEPARATION_WEIGHT: f32 = 2.0;
+const COHESION_WEIGHT: f32 = 0.15;
+
+fn bounded(target: Vec2) -> Vec2 {
+    target.clamp(Vec2 { x: MIN_X, y: MIN_Y }, Vec2 { x: MAX_X, y: MAX_Y })
+}
+
+impl Ghost {
+
+pub fn brain(&mut self, hero_pos: Vec2, neighbours: &[Neighbour]) {
+    if self.state != MonsterState::Idle {
+        return;
+    }
+
+    let to_hero = hero_pos + Vec2 { x: 0.0, y: 8.0 } - self.body.position;
+    let distance = to_hero.length();
+
+    if self.flee_timer > 0 {
+        self.flee_timer -= 1;
+    }
+
+    // An ambush springs as soon as the hero is close enough
+    if self.behaviour == Behaviour::Ambush && distance < AMBUSH_TRIGGER {
+        self.sprite.set_transparency(1.0);
+        self.start_lunge(to_hero);
+        return;
+    }
+
+    if self.attack_cooldown > 0 {
+        self.attack_cooldown -= 1;
+    }
+    else if self.behaviour != Behaviour::Retreat && self.behaviour != Behaviour::Ambush {
+        if distance < 50.0 && gen_range(0, 100) < 40 {
+            self.state = MonsterState::ScreamWindUp;
+            self.attack_timer = SCREAM_WIND_UP;
+            return;
+        }
+        else if distance < 120.0 {
+            self.start_lunge(to_hero);
+            return;
+        }
+    }
+
+    self.think_timer -= 1;
+    if self.think_timer <= 0 {
+        self.think_timer = THINK_TIME + gen_range(0, 20);
+        self.choose_behaviour(distance, neighbours);
+    }
+
+    self.steer(hero_pos, neighbours);
+}
+
+fn start_lunge(&mut self, to_hero: Vec2) {
+    self.state = MonsterState::LungeWindUp;
+    self.attack_timer = LUNGE_WIND_UP;
+    self.lunge_dir = to_hero.normalize_or_zero();
+}
+
+/// Utility selector: every behaviour gets a score, the best one wins
+fn choose_behaviour(&mut self, distance: f32, neighbours: &[Neighbour]) {
+    let nearby: Vec<Behaviour> = neighbours.iter()
+        .filter(|(p, _)| p.distance(self.body.position) < NEIGHBOURHOOD)
+        .map(|(_, b)| *b)
+        .collect();
+
+    let candidates = [
+        Behaviour::UpDown { yo: self.body.position.y, speed: 0.01, dt: 0.0 },
+        Behaviour::Approach,
+        Behaviour::Orbit { angle: 0.0, dir: if gen_range(0, 2) == 0 {-1.0} else {1.0} },
+        Behaviour::Retreat,
+        Behaviour::CircleOverhead { angle: 0.0 },
+        Behaviour::Ambush,
+    ];
+
+    let mut best = (self.behaviour, f32::MIN);
+    for candidate in candidates {
+        let value = self.score(candidate, distance, &nearby) + gen_range(0.0, 0.1);
+        if value > best.1 {
+            best = (candidate, value);
+        }
+    }
+
+    // Keep the running state of the behaviour if it doesn't change
+    if std::mem::discriminant(&best.0) != std::mem::discriminant(&self.behaviour) {
+        if self.behaviour == Behaviour::Ambush {
+            self.sprite.set_transparency(1.0);
+        }
+        self.behaviour = best.0;
+    }
+}
+
+fn score(&self, behaviour: Behaviour, distance: f32, nearby: &[Behaviour]) -> f32 {
+    let health = self.health as f32 / self.max_health as f32;
+    let count = |f: fn(&Behaviour) -> bool| nearby.iter().filter(|b| f(b)).count() as f32;
+
+    match behaviour {
+        Behaviour::StandBy => 0.0,
+        Behaviour::UpDown { .. } => 0.2,
+        Behaviour::Approach => {
+            // Don't rush all together
+            let rushing = count(|b| *b == Behaviour::Approach);
+            0.3 + (distance - 60.0).max(0.0) / 150.0 - 0.3 * rushing
+        },
+        Behaviour::Orbit { .. } => {
+            // Keep the hero busy while someone else presses
+            let pressing = count(|b| matches!(b, Behaviour::Approach | Behaviour::CircleOverhead { .. }));
+            if distance < 130.0 {0.35 + 0.2 * pressing} else {0.1}
+        },
+        Behaviour::Retreat => {
+            if self.flee_timer > 0 {0.4 + (1.0 - health)} else {0.0}
+        },
+        Behaviour::CircleOverhead { .. } => {
+            // Only one ghost over the head at a time
+            let circling = count(|b| matches!(b, Behaviour::CircleOverhead { .. }));
+            if circling > 0.0 || distance > 140.0 {0.0} else {0.45}
+        },
+        Behaviour::Ambush => {
+            let ambushing = count(|b| *b == Behaviour::Ambush);
+            if distance > 140.0 && ambushing == 0.0 {0.4 + 0.3 * health} else {0.0}
+        },
+    }
+}
+
+/// Move according to the current behaviour, without stacking on the others
+fn steer(&mut self, hero_pos: Vec2, neighbours: &[Neighbour]) {
+    let position = self.body.position;
+    let desired = match self.behaviour {
+        Behaviour::StandBy => {
+            if gen_range(0, 100) < 2 {
+                self.behaviour = Behaviour::UpDown { yo: position.y, speed: 0.01, dt: 0.0 };
+            }
+            Vec2::ZERO
+        },
+        Behaviour::UpDown { yo, speed, dt } => {
+            // Steer toward the point of the sine bob instead of teleporting on it
+            self.behaviour = Behaviour::UpDown { yo, speed: 0.01, dt: dt + speed };
+            let target = Vec2 { x: position.x, y: yo + 15.0 * (dt + speed).sin() };
+            steering::arrive(position, target, MAX_SPEED, SLOWING_RADIUS)
+        },
+        Behaviour::Approach => {
+            // Stop in front of the hero rather than on top of it
+            let side = if position.x < hero_pos.x {-1.0} else {1.0};
+            let target = hero_pos + Vec2 { x: 20.0 * side, y: 0.0 };
+            steering::arrive(position, bounded(target), MAX_SPEED, SLOWING_RADIUS)
+        },
+        Behaviour::Orbit { angle, dir } => {
+            self.behaviour = Behaviour::Orbit { angle: angle + 0.02 * dir, dir };
+            let target = hero_pos + 70.0 * Vec2 { x: angle.cos(), y: 0.4 * angle.sin() };
+            steering::arrive(position, bounded(target), MAX_SPEED, SLOWING_RADIUS)
+        },
+        Behaviour::Retreat => steering::flee(position, hero_pos, MAX_SPEED),
+        Behaviour::CircleOverhead { angle } => {
+            self.behaviour = Behaviour::CircleOverhead { angle: angle + 0.04 };
+            let target = hero_pos + Vec2 { x: 30.0 * angle.cos(), y: -45.0 + 6.0 * angle.sin() };
+            steering::arrive(position, bounded(target), MAX_SPEED, SLOWING_RADIUS)
+        },
+        Behaviour::Ambush => {
+            self.sprite.set_transparency(0.25);
+            steering::arrive(position, Vec2 { x: position.x, y: MAX_Y }, MAX_SPEED, SLOWING_RADIUS)
+        },
+    };
+
+    let others: Vec<Vec2> = neighbours.iter().map(|(p, _)| *p).collect();
+    let bounds = Rect { x: MIN_X, y: MIN_Y, w: MAX_X - MIN_X, h: MAX_Y - MIN_Y };
+    let desired = desired
+        + SEPARATION_WEIGHT * MAX_SPEED * steering::separation(position, &others, SEPARATION_RADIUS)
+        + COHESION_WEIGHT * steering::cohesion(position, &others, NEIGHBOURHOOD, MAX_SPEED)
+        + steering::contain(position, bounds, MAX_SPEED);
+
+    self.body.velocity = steering::apply(self.body.velocity, desired, MAX_FORCE, MAX_SPEED);
+}
+}
@@ -0,0 +1,34 @@
+use macroquad::prelude::Rect;
+
+use crate::collision::mirror_x;
+use super::MonsterState;
+
+// The head, up front, and the tail trailing behind and below it
+const HEAD: Rect = Rect { x: 29.0, y: 19.0, w: 11.0, h: 11.0 };
+const TAIL: Rect = Rect { x: 24.0, y: 30.0, w: 11.0, h: 11.0 };
+// What is left of the ghost while it rises from the ground or fades away
+const SMALL: Rect = Rect { x: 27.0, y: 36.0, w: 11.0, h: 8.0 };
+
+/// Boxes where a ghost can be hurt, for a frame of the animation of `state`
+pub fn get_hurt_boxes(state: MonsterState, frame: i32, flip_x: bool) -> Vec<Rect> {
+    let boxes = match state {
+        MonsterState::Birth => {
+            match frame {
+                0..=3 => vec![],
+                4..=7 => vec![SMALL],
+                _ => vec![HEAD, TAIL],
+            }
+        },
+        MonsterState::Dead => {
+            match frame {
+                0..=2 => vec![HEAD, TAIL],
+                3 | 4 => vec![SMALL],
+                _ => vec![],
+            }
+        },
+        _ => vec![HEAD, TAIL],
+    };
+
+    // Make symetric boxes if sprite is flip
+    boxes.into_iter().map(|b| if flip_x {mirror_x(b, 64.0)} else {b}).collect()
+}
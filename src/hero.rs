@@ -2,40 +2,134 @@ use macroquad::prelude::*;
 
 use std::collections::HashMap;
 
+use crate::collision::Collider;
+use crate::physics::Body;
 use crate::sound_system::SoundBox;
 use crate::sprite::{AnimationData, AnimatedSprite};
 use crate::controls;
-use crate::ghost::Ghost;
+use crate::beat::{BeatClock, BeatGrade};
+use crate::difficulty::DifficultySettings;
+use crate::enemy::{EnemyId, Hit};
 use attack::AttackType;
 
-use self::attack::{get_hit_box, get_hit_point};
+use self::attack::{charge_level, get_hit_box, get_hit_point, get_hit_reaction, get_hit_rules, HitRegistry, DASH_TIME, MAX_CHARGE_LEVEL};
+use self::combo::{ComboGraph, ComboInput};
 use state::State;
 
 mod attack;
+mod combo;
+mod hurtbox;
 mod state;
 
+// Frames of invulnerability once the hit animation is over
+const INVULNERABILITY: i32 = 60;
+// Frames at the start of a parry during which it catches an enemy
+const PARRY_WINDOW: i32 = 12;
+// Frames of invulnerability after a successful parry
+const PARRY_INVULNERABILITY: i32 = 30;
+
+const JUMP_SPEED: f32 = 8.0;
+const DOUBLE_JUMP_SPEED: f32 = 7.0;
+// Frames after leaving the floor during which the hero can still jump
+const COYOTE_TIME: i32 = 6;
+// Part of the upward speed kept when Space is released early
+const JUMP_CUT: f32 = 0.4;
+// Fastest fall while sliding down a wall
+const WALL_SLIDE_SPEED: f32 = 1.0;
+const WALL_JUMP_PUSH: f32 = 4.0;
+const WALL_JUMP_SPEED: f32 = 7.5;
+// Frames of a wall jump during which the horizontal input is ignored
+const WALL_JUMP_LOCK: i32 = 10;
+
+// Dashes allowed between two landings
+const AIR_DASHES: i32 = 1;
+// Frames between two afterimages of a dash, and lifetime of one
+const AFTERIMAGE_EVERY: i32 = 2;
+const AFTERIMAGE_TIME: i32 = 16;
+
+// Breath needed to use the last breath, which spends half of it
+const LAST_BREATH_MIN: i32 = 180;
+
+// Fall speed of a down plunge, and upward speed of its bounce on an enemy
+const PLUNGE_SPEED: f32 = 6.0;
+const POGO_SPEED: f32 = 7.0;
+
+/// Moves unlocked along the run
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Ability {
+    DoubleJump,
+}
+
+impl Ability {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "double_jump" => Some(Ability::DoubleJump),
+            _ => None,
+        }
+    }
+}
 
 
 pub struct Hero {
-    pub position: Vec2,
+    body: Body,
     direction: f32,
-    velocity: Vec2,
     state: State,
     animations: HashMap<State, AnimationData>,
     pub sprite: AnimatedSprite,
 
     health: i32,
+    max_health: i32,
+    attack_drain: i32,
+    knockback: f32,
+    damage_scale: f32,
+    finisher_breath: i32,
+    beat_breath: i32,
+    parry_breath: i32,
+
+    combos: ComboGraph,
+    combo: i32,
+    combo_timer: i32,
+    // The current attack was reached through a finisher link
+    finisher: bool,
+    // The current attack was cancelled into a new one this frame
+    cancelled: bool,
+
+    // Timing of the current attack against the heartbeat
+    attack_grade: Option<(u32, BeatGrade)>,
+    // Enemies already hit by the current attack
+    hits: HitRegistry,
 
-    collision_box: Rect,
-
-    on_the_floor: bool,
     hited: bool,
     hitable: bool,
+    invulnerable: i32,
+    parry_timer: i32,
+    parried: bool,
+
+    coyote_timer: i32,
+    // Space is still held since the last jump
+    jump_held: bool,
+    double_jump: bool,
+    // The double jump has not been used since the last landing
+    air_jump: bool,
+    // Horizontal normal of the wall touched during the last step
+    wall: Option<f32>,
+    wall_jump_timer: i32,
+    // Jumps decided by the last update, for the state manager
+    double_jumped: bool,
+    wall_jumped: bool,
+
+    dash_iframes: i32,
+    dash_iframes_time: i32,
+    dash_cooldown: i32,
+    dash_cooldown_time: i32,
+    air_dashes: i32,
+    // Copies of the sprite left behind by a dash, with their remaining frames
+    afterimages: Vec<(AnimatedSprite, i32)>,
     attack: Option<AttackType>
 }
 
 impl Hero {
-    pub fn new(x: f32, y: f32, life_time: i32) -> Self {
+    pub fn new(x: f32, y: f32, settings: &DifficultySettings) -> Self {
         let position = Vec2 { x, y };
         let animations = HashMap::from([
             (State::AirDash, AnimationData{x: 0, y: 0, h: 64, w: 64, frames: 7, speed: 4, pivot_x: 0, pivot_y: 0}),
@@ -43,12 +137,22 @@ impl Hero {
             (State::Walk, AnimationData{x: 0, y: 128, h: 64, w: 64, frames: 8, speed: 4, pivot_x: 0, pivot_y: 0}),
             (State::Idle, AnimationData{x: 0, y: 192, h: 64, w: 64, frames: 8, speed: 4, pivot_x: 0, pivot_y: 0}),
             (State::Jump, AnimationData{x: 0, y: 256, h: 64, w: 64, frames: 12, speed: 4, pivot_x: 0, pivot_y: 0}),
+            (State::DoubleJump, AnimationData{x: 320, y: 256, h: 64, w: 64, frames: 4, speed: 3, pivot_x: 0, pivot_y: 0}),
+            (State::WallSlide, AnimationData{x: 448, y: 256, h: 64, w: 64, frames: 2, speed: 8, pivot_x: 0, pivot_y: 0}),
+            (State::WallJump, AnimationData{x: 128, y: 256, h: 64, w: 64, frames: 7, speed: 3, pivot_x: 0, pivot_y: 0}),
             (State::AttackDouble, AnimationData{x: 0, y: 384, h: 64, w: 64, frames: 19, speed: 2, pivot_x: 0, pivot_y: 0}),
             (State::AttackOne, AnimationData{x: 0, y: 448, h: 64, w: 64, frames: 17, speed: 4, pivot_x: 0, pivot_y: 0}),
             (State::RepeatAttack, AnimationData{x: 640, y: 448, h: 64, w: 64, frames: 7, speed: 4, pivot_x: 0, pivot_y: 0}),
             (State::Hit, AnimationData{x: 128, y: 512, h: 64, w: 64, frames: 5, speed: 2, pivot_x: 0, pivot_y: 0}),
             (State::Dying, AnimationData{x: 384, y: 320, h: 64, w: 64, frames: 13, speed: 10, pivot_x: 0, pivot_y: 0}),
             (State::Dead, AnimationData{x: 1152, y: 320, h: 64, w: 64, frames: 1, speed: 2, pivot_x: 0, pivot_y: 0}),
+            (State::Parry, AnimationData{x: 0, y: 640, h: 64, w: 64, frames: 8, speed: 3, pivot_x: 0, pivot_y: 0}),
+            (State::ParryRecover, AnimationData{x: 0, y: 512, h: 64, w: 64, frames: 2, speed: 15, pivot_x: 0, pivot_y: 0}),
+            (State::UpSlash, AnimationData{x: 704, y: 448, h: 64, w: 64, frames: 6, speed: 3, pivot_x: 0, pivot_y: 0}),
+            (State::AirSlash, AnimationData{x: 320, y: 384, h: 64, w: 64, frames: 6, speed: 2, pivot_x: 0, pivot_y: 0}),
+            (State::DownPlunge, AnimationData{x: 256, y: 256, h: 64, w: 64, frames: 2, speed: 4, pivot_x: 0, pivot_y: 0}),
+            (State::Charge, AnimationData{x: 0, y: 576, h: 64, w: 64, frames: 10, speed: 4, pivot_x: 0, pivot_y: 0}),
+            (State::ChargedHeavy, AnimationData{x: 0, y: 448, h: 64, w: 64, frames: 17, speed: 4, pivot_x: 0, pivot_y: 0}),
         ]);
 
         let state = State::Idle;
@@ -57,157 +161,483 @@ impl Hero {
 
 
         Self {
-            position,
+            body: Body::new(position, Rect { x: 27.0, y: 28.0, w: 10.0, h: 20.0 }, 1.0, Vec2::ONE),
             direction: 0.0,
-            velocity: Vec2::ZERO,
             state,
             animations,
             sprite,
-            collision_box: Rect { x: 27.0, y: 28.0, w: 10.0, h: 20.0 },
 
-            health: 60 * life_time,
+            health: 60 * settings.life_time,
+            max_health: 60 * settings.life_time,
+            attack_drain: settings.attack_drain,
+            knockback: settings.knockback,
+            damage_scale: settings.damage_scale,
+            finisher_breath: settings.finisher_breath,
+            beat_breath: settings.beat_breath,
+            parry_breath: settings.parry_breath,
+
+            combos: ComboGraph::parse(include_str!("../assets/data/combos.txt")).expect("Invalid combo script"),
+            combo: 0,
+            combo_timer: 0,
+            finisher: false,
+            cancelled: false,
+
+            attack_grade: None,
+            hits: HitRegistry::default(),
 
-            on_the_floor: false,
             hited: false,
             hitable: true,
+            invulnerable: 0,
+            parry_timer: 0,
+            parried: false,
+
+            coyote_timer: 0,
+            jump_held: false,
+            double_jump: false,
+            air_jump: false,
+            wall: None,
+            wall_jump_timer: 0,
+            double_jumped: false,
+            wall_jumped: false,
+
+            dash_iframes: 0,
+            dash_iframes_time: settings.dash_iframes,
+            dash_cooldown: 0,
+            dash_cooldown_time: settings.dash_cooldown,
+            air_dashes: AIR_DASHES,
+            afterimages: Vec::new(),
             attack: None,
         }
     }
 
-    pub fn update(&mut self, monsters: &mut Vec<Ghost>, colliders: &Vec<Rect>, sound_bank: &SoundBox) {
-        // Check monster collision
-
-        self.hited = false;
-        for monster in monsters.iter_mut() {
-
-            if let Some(attack) = &self.attack {
-                match self.get_hit_box(attack) {
-                    Some(hbox) => {
-                        if monster.is_hitable() && monster.get_collision_box(0.0, 0.0).overlaps(&hbox) {
-                            monster.hit(get_hit_point(&attack));
+    /// Resolve the current attack, or the body contact, against an enemy.
+    /// `contact_damage` is the breath lost by touching it, if it is harmful.
+    /// Return the hit landing on the enemy, if any, with the beat grade of the attack
+    pub fn check_enemy(&mut self, target: EnemyId, hurtbox: Rect, position: Vec2, hitable: bool, contact_damage: Option<i32>) -> Option<(Hit, Option<BeatGrade>)> {
+        if let Some(attack) = &self.attack {
+            if let Some(hbox) = self.get_hit_box(attack) {
+                if hitable && hurtbox.overlaps(&hbox) && self.hits.can_hit(target, &get_hit_rules(attack)) {
+                    self.hits.record(target);
+                    let mut damage = get_hit_point(attack);
+                    let reaction = get_hit_reaction(attack);
+                    if let Some((_, grade)) = self.attack_grade {
+                        damage = grade.apply(damage);
+                        if grade == BeatGrade::Perfect {
+                            self.restore_breath(self.beat_breath);
                         }
-
-                    },
-                    None => {}
+                    }
+                    if self.finisher {
+                        self.restore_breath(self.finisher_breath);
+                    }
+                    // Long combos hit harder
+                    damage += self.combo / self.combos.bonus_every;
+                    self.combo += 1;
+                    self.combo_timer = self.combos.timeout;
+                    if matches!(self.attack, Some(AttackType::DownPlunge)) {
+                        self.pogo();
+                    }
+                    // Pushed away from the hero
+                    let side = if hurtbox.center().x < self.get_hurtbox().center().x {-1.0} else {1.0};
+                    let hit = Hit {
+                        damage,
+                        knockback: Vec2 { x: side * reaction.knockback.x, y: reaction.knockback.y },
+                        stun: reaction.stun,
+                        launch: reaction.launch,
+                    };
+                    return Some((hit, self.attack_grade.map(|(_, grade)| grade)));
                 }
-
             }
+        }
 
-            // Check body to body collision
-            else if monster.is_hitable() && self.state != State::Hit && self.get_collision_box(0.0, 0.0).overlaps(&monster.get_collision_box(0.0, 0.0)) {
-                self.hited = true;
-                let bump_dir = (monster.position - self.position).normalize();
-                self.velocity = -8.0 * bump_dir;
+        // Check body to body collision
+        else if let Some(damage) = contact_damage {
+            if self.get_hurtbox().overlaps(&hurtbox) {
+                self.take_hit(position, damage);
             }
+        }
+        None
+    }
 
+    /// Bounce up off an enemy hit by a down plunge, ready to jump and dash again
+    fn pogo(&mut self) {
+        self.attack = None;
+        self.body.velocity.y = -POGO_SPEED;
+        self.jump_held = false;
+        self.air_jump = true;
+        self.air_dashes = AIR_DASHES;
+    }
 
+    /// Lose some breath and get bumped away from `source`
+    pub fn take_hit(&mut self, source: Vec2, damage: i32) {
+        if !self.hitable || self.is_dodging() {
+            return;
         }
+        self.hited = true;
+        self.hitable = false;
+        self.jump_held = false;
+        self.health = (self.health - (damage as f32 * self.damage_scale) as i32).max(0);
+        let bump_dir = (source - self.body.position).normalize_or_zero();
+        self.body.velocity = -self.knockback * bump_dir;
+    }
 
+    /// Grade the current attack against the beat, once, when it becomes active.
+    /// The grade only counts for the hits it lands
+    pub fn grade_attack(&mut self, beat: &BeatClock) {
+        let Some(attack) = &self.attack else {
+            self.attack_grade = None;
+            return;
+        };
+        // A combo follow-up is graded on its own
+        let id = self.hits.attack_id;
+        if self.attack_grade.is_some_and(|(graded, _)| graded == id) || self.get_hit_box(attack).is_none() {
+            return;
+        }
+        self.attack_grade = Some((id, beat.grade()));
+    }
 
-        self.state_manager(sound_bank);
+    /// Box catching enemies during the parry window
+    pub fn get_parry_box(&self) -> Option<Rect> {
+        if self.state == State::Parry && self.parry_timer > 0 && !self.parried {
+            let body = self.get_hurtbox();
+            Some(Rect { x: body.x - 6.0, y: body.y - 4.0, w: body.w + 12.0, h: body.h + 8.0 })
+        }
+        else {
+            None
+        }
+    }
 
-        // Gravity
-        self.velocity.y += 0.5;
+    /// The parry caught something: breathe in and stay out of reach for a moment
+    pub fn parry(&mut self) {
+        self.parried = true;
+        self.parry_timer = 0;
+        self.restore_breath(self.parry_breath);
+        self.hitable = false;
+        self.invulnerable = PARRY_INVULNERABILITY;
+    }
+
+    /// The start of a dash makes the hero pass through enemies and projectiles
+    pub fn is_dodging(&self) -> bool {
+        self.dash_iframes > 0
+    }
+
+    /// Part of the dash cooldown still to wait, from 1 right after a dash to 0 when ready
+    pub fn get_dash_cooldown(&self) -> f32 {
+        self.dash_cooldown as f32 / self.dash_cooldown_time.max(1) as f32
+    }
+
+    /// Charge level of the heavy attack being charged, if any
+    pub fn get_charge_level(&self) -> Option<i32> {
+        match self.attack {
+            Some(AttackType::Charging { frames }) => Some(charge_level(frames)),
+            _ => None,
+        }
+    }
+
+    /// Spend half of the remaining breath at once. Return the breath spent, if there was enough
+    pub fn last_breath(&mut self) -> Option<i32> {
+        if self.health < LAST_BREATH_MIN || matches!(self.state, State::Hit | State::Dying | State::Dead) {
+            return None;
+        }
+        let spent = self.health / 2;
+        self.health -= spent;
+        Some(spent)
+    }
 
-        if self.state != State::Hit && self.state != State::Dying && self.state != State::Dead {
+    fn can_dash(&self) -> bool {
+        self.dash_cooldown == 0 && (self.body.grounded || self.air_dashes > 0)
+    }
+
+    fn start_dash(&mut self) {
+        self.dash_cooldown = self.dash_cooldown_time;
+        self.dash_iframes = self.dash_iframes_time;
+        if !self.body.grounded {
+            self.air_dashes -= 1;
+        }
+    }
+
+    /// The active hitbox of the double slash, which can cut projectiles
+    pub fn get_deflect_box(&self) -> Option<Rect> {
+        match &self.attack {
+            Some(attack @ AttackType::Double) => self.get_hit_box(attack),
+            _ => None,
+        }
+    }
+
+    pub fn update(&mut self, colliders: &[Collider], sound_bank: &SoundBox) {
+        self.state_manager(sound_bank);
+
+        if !matches!(self.state, State::Hit | State::Dying | State::Dead | State::Parry | State::ParryRecover) {
             self.direction = controls::get_x_axis();
             
             if let Some(AttackType::AttackDash { timer: _, dir }) = &self.attack {
                 self.direction = *dir;
-                self.velocity.x = self.direction * 6.0;
+                self.body.velocity.x = self.direction * 6.0;
             }
             else if let Some(AttackType::AttackAirDash { timer: _, dir }) = &self.attack {
                 self.direction = *dir;
-                self.velocity.x = self.direction * 8.0;
+                self.body.velocity.x = self.direction * 8.0;
             } 
-            else if let Some(AttackType::Heavy) = &self.attack {
+            else if let Some(AttackType::DownPlunge) = &self.attack {
+                self.direction = 0.0;
+                self.body.velocity = Vec2 { x: 0.0, y: PLUNGE_SPEED };
+            }
+            else if let Some(AttackType::Heavy | AttackType::Charging { .. } | AttackType::ChargedHeavy { .. }) = &self.attack {
                 self.direction = 0.0;
-                self.velocity.x = 0.0
+                self.body.velocity.x = 0.0
+            }
+            else if self.wall_jump_timer > 0 {
+                // Pushed away from the wall, whatever the input
+                self.wall_jump_timer -= 1;
+                self.direction = 0.0;
+                self.body.velocity.x *= 0.8;
             }
             else if self.direction != 0.0 {
-                self.velocity.x = self.direction * 2.0;
+                self.body.velocity.x = self.direction * 2.0;
             }
             else {
-                self.velocity.x *= 0.8;
+                self.body.velocity.x *= 0.8;
             }
 
 
-            if self.on_the_floor && is_key_pressed(KeyCode::Space) {
-                // Jump
-                self.velocity.y = -8.0;
-                self.on_the_floor = false;
+            if self.body.grounded {
+                self.coyote_timer = COYOTE_TIME;
+                self.air_jump = true;
+                self.air_dashes = AIR_DASHES;
+            }
+            else if self.coyote_timer > 0 {
+                self.coyote_timer -= 1;
+            }
+
+            if is_key_pressed(KeyCode::Space) {
+                if self.body.grounded && controls::get_y_axis() > 0.0 {
+                    // Drop through a one-way platform
+                    self.body.drop_through = true;
+                }
+                else if self.body.grounded || self.coyote_timer > 0 {
+                    self.jump(JUMP_SPEED);
+                }
+                else if let Some(normal) = self.wall {
+                    self.jump(WALL_JUMP_SPEED);
+                    self.body.velocity.x = normal * WALL_JUMP_PUSH;
+                    self.sprite.flip_x = normal < 0.0;
+                    self.wall_jump_timer = WALL_JUMP_LOCK;
+                    self.air_jump = true;
+                    self.air_dashes = AIR_DASHES;
+                    self.wall_jumped = true;
+                }
+                else if self.double_jump && self.air_jump {
+                    self.jump(DOUBLE_JUMP_SPEED);
+                    self.air_jump = false;
+                    self.double_jumped = true;
+                }
+            }
+
+            // Letting go of Space early cuts the jump
+            if self.jump_held && (!is_key_down(KeyCode::Space) || self.body.velocity.y >= 0.0) {
+                if self.body.velocity.y < 0.0 {
+                    self.body.velocity.y *= JUMP_CUT;
+                }
+                self.jump_held = false;
+            }
+
+            let attacking = self.attack.is_some();
+
+            // Hold C to charge the heavy attack, release to strike
+            if let Some(AttackType::Charging { frames }) = self.attack {
+                if is_key_down(KeyCode::C) {
+                    self.attack = Some(AttackType::Charging { frames: frames + 1 });
+                }
+                else {
+                    let level = charge_level(frames);
+                    self.attack = Some(if level > 0 {AttackType::ChargedHeavy { level }} else {AttackType::Heavy});
+                    self.cancelled = true;
+                }
             }
 
             // Attack and combo management
             match &self.attack {
                 None => {
                     if is_key_pressed(KeyCode::V) {
-                        if self.direction != 0.0 && self.on_the_floor {
-                            self.attack = Some(AttackType::AttackDash{timer: 10, dir: self.direction});
+                        if self.direction != 0.0 {
+                            if self.can_dash() {
+                                self.attack = Some(if self.body.grounded {
+                                    AttackType::AttackDash{timer: DASH_TIME, dir: self.direction}
+                                }
+                                else {
+                                    AttackType::AttackAirDash{timer: DASH_TIME, dir: self.direction}
+                                });
+                                self.start_dash();
+                            }
                         }
-                        else if self.direction != 0.0 && !self.on_the_floor {
-                            self.attack = Some(AttackType::AttackAirDash{timer: 10, dir: self.direction});
-
+                        else if !self.body.grounded && controls::get_y_axis() > 0.0 {
+                            self.attack = Some(AttackType::DownPlunge);
+                        }
+                        else if !self.body.grounded {
+                            self.attack = Some(AttackType::AirSlash);
                         }
                         else {
                             self.attack = Some(AttackType::Double);
                         }
                     }
-                    if is_key_pressed(KeyCode::C) {self.attack = Some(AttackType::Heavy)}
+                    if is_key_pressed(KeyCode::C) {
+                        self.attack = Some(if controls::get_y_axis() < 0.0 {AttackType::UpSlash} else {AttackType::Charging { frames: 0 }});
+                    }
+                    if is_key_pressed(KeyCode::X) && self.body.grounded && matches!(self.state, State::Idle | State::Walk) {
+                        self.parry_timer = PARRY_WINDOW;
+                        self.parried = false;
+                    }
                 },
                 Some(attack) => {
-                    match attack {
-                        AttackType::Heavy => {
-                            if self.sprite.current_frame > 13 && is_key_pressed(KeyCode::C){
-                                self.attack = Some(AttackType::RepeatHeavy)
+                    // Cancel into the next attack of the combo graph. The input is read
+                    // from the stick, the hero can't move during some attacks
+                    let axis = controls::get_x_axis();
+                    let link = ComboInput::read(axis)
+                        .and_then(|input| self.combos.follow(attack.name(), input, self.sprite.current_frame));
+                    if let Some(link) = link {
+                        let dir = if axis != 0.0 {axis} else if self.sprite.flip_x {-1.0} else {1.0};
+                        let next = AttackType::from_name(&link.to, dir);
+                        let finisher = link.finisher;
+                        let dash = matches!(next, Some(AttackType::AttackDash { .. }) | Some(AttackType::AttackAirDash { .. }));
+                        if !dash || self.can_dash() {
+                            if dash {
+                                self.start_dash();
                             }
+                            self.attack = next;
+                            self.finisher = finisher;
+                            self.cancelled = true;
                         }
-                        _ => {}
                     }
-
                 }
             }
+
+            // Every new attack, cancel included, can hit again
+            if self.attack.is_some() && (!attacking || self.cancelled) {
+                self.hits.start();
+            }
         }
         else {
-            self.velocity *= 0.7;
+            self.body.velocity *= 0.7;
         }
+        self.hits.tick();
+
 
+        if self.dash_cooldown > 0 {
+            self.dash_cooldown -= 1;
+        }
+        if self.dash_iframes > 0 {
+            self.dash_iframes -= 1;
+        }
+
+        if self.combo_timer > 0 {
+            self.combo_timer -= 1;
+            if self.combo_timer == 0 {
+                self.combo = 0;
+            }
+        }
 
         // Health
         self.health -= 1;
-        match &self.attack {
-            Some(_attack) => self.health -= 1,
-            None => {}
+        if self.attack.is_some() {
+            self.health -= self.attack_drain;
+        }
+        // Charging takes the breath away faster at each level
+        if let Some(AttackType::Charging { frames }) = self.attack {
+            self.health -= 1 + charge_level(frames);
         }
         if self.health < 0 {
             self.health = 0;
         }
 
 
-        // Check collision with scene
-        for collider in colliders.iter() {
-            if self.get_collision_box(0.0, self.velocity.y).overlaps(collider){
-                self.velocity.y = 0.0;
-                self.on_the_floor = true;
+        // Blink while invulnerable
+        if self.invulnerable > 0 {
+            self.invulnerable -= 1;
+            self.sprite.set_transparency(if self.invulnerable % 8 < 4 {0.3} else {1.0});
+            if self.invulnerable == 0 {
+                self.hitable = true;
+                self.sprite.set_transparency(1.0);
             }
-            if self.get_collision_box(self.velocity.x, 0.0).overlaps(collider){
-                self.velocity.x = 0.0;
+        }
+
+        // Glow while charging, up to the strike
+        let glow = match self.attack {
+            Some(AttackType::Charging { frames }) => charge_level(frames),
+            Some(AttackType::ChargedHeavy { level }) => level,
+            _ => 0,
+        };
+        let pulse = 0.8 + 0.2 * (get_time() as f32 * 12.0).sin();
+        let t = pulse * glow as f32 / MAX_CHARGE_LEVEL as f32;
+        self.sprite.set_tint(Color::new(1.0, 1.0 - 0.33 * t, 1.0 - 0.63 * t, 1.0));
+
+        // Move against the scene
+        self.body.max_fall_speed = (self.state == State::WallSlide).then_some(WALL_SLIDE_SPEED);
+        let movement = self.body.step(colliders);
+        self.wall = if self.body.grounded {None} else {movement.wall_normal()};
+
+
+        self.sprite.set_position_to(self.body.position);
+
+        // Leave a trail of fading copies behind a dash
+        for (_, timer) in self.afterimages.iter_mut() {
+            *timer -= 1;
+        }
+        self.afterimages.retain(|(_, timer)| *timer > 0);
+        if let Some(AttackType::AttackDash { timer, .. } | AttackType::AttackAirDash { timer, .. }) = &self.attack {
+            if timer % AFTERIMAGE_EVERY == 0 {
+                let mut image = self.sprite;
+                image.pause();
+                self.afterimages.push((image, AFTERIMAGE_TIME));
             }
+        }
+    }
 
+    pub fn draw_afterimages(&mut self, texture: Texture2D) {
+        for (image, timer) in self.afterimages.iter_mut() {
+            image.set_transparency(0.5 * *timer as f32 / AFTERIMAGE_TIME as f32);
+            image.draw_sprite(texture, Vec2::ZERO, 1.0);
         }
-        // position update
-        self.position += self.velocity;
+    }
 
 
-        self.sprite.set_position_to(self.position);
+    fn jump(&mut self, speed: f32) {
+        self.body.velocity.y = -speed;
+        self.body.grounded = false;
+        self.coyote_timer = 0;
+        self.jump_held = true;
     }
 
+    /// Pushing against a wall while falling along it
+    fn is_wall_sliding(&self) -> bool {
+        !self.body.grounded && self.body.velocity.y > 0.0 && self.wall.is_some_and(|normal| self.direction == -normal)
+    }
+
+    pub fn unlock(&mut self, ability: Ability) {
+        match ability {
+            Ability::DoubleJump => self.double_jump = true,
+        }
+    }
 
     pub fn get_health(&self) -> i32 {
         self.health
         
     }
 
+    /// Hits landed in a row, without waiting too long between two
+    pub fn get_combo(&self) -> i32 {
+        self.combo
+    }
+
+    pub fn get_max_health(&self) -> i32 {
+        self.max_health
+    }
+
+    /// Give back some breath, without going over the starting amount
+    pub fn restore_breath(&mut self, amount: i32) {
+        if self.health > 0 {
+            self.health = (self.health + amount).min(self.max_health);
+        }
+    }
+
     pub fn is_dead(&self) -> bool {
         match self.state {
             State::Dead => true,
@@ -215,16 +645,22 @@ impl Hero {
         }
     }
 
-    pub fn get_collision_box(&self, dx: f32, dy: f32) -> Rect {
-        Rect { x: self.position.x + self.collision_box.x + dx, y: self.position.y + self.collision_box.y + dy, w: self.collision_box.w, h: self.collision_box.h }
+    pub fn position(&self) -> Vec2 {
+        self.body.position
+    }
+
+    /// Where the hero can be hurt, following the pose of the current frame
+    pub fn get_hurtbox(&self) -> Rect {
+        let h_box = hurtbox::get_hurt_box(self.state, self.sprite.current_frame, self.sprite.flip_x);
+        Rect { x: h_box.x + self.body.position.x, y: h_box.y + self.body.position.y, ..h_box }
     }
 
     pub fn get_hit_box(&self, attack: &AttackType) -> Option<Rect> {
         match get_hit_box(&attack, self.sprite.current_frame, self.sprite.flip_x) {
             Some(hbox) => {
                 Some(Rect{
-                    x: hbox.x + self.position.x,
-                    y: hbox.y + self.position.y,
+                    x: hbox.x + self.body.position.x,
+                    y: hbox.y + self.body.position.y,
                     w: hbox.w,
                     h: hbox.h
                 })
@@ -236,7 +672,7 @@ impl Hero {
     //pub fn debug_hitbox(&self) {
     //    if let Some(attack) = &self.attack {
     //        if let Some(h_box) = get_hit_box(attack, self.sprite.current_frame, self.sprite.flip_x) {
-    //            draw_rectangle_lines(h_box.x + self.position.x, h_box.y + self.position.y, h_box.w, h_box.h, 1.0, YELLOW);
+    //            draw_rectangle_lines(h_box.x + self.body.position.x, h_box.y + self.body.position.y, h_box.w, h_box.h, 1.0, YELLOW);
     //        }
     //    }
     //}
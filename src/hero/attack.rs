@@ -1,4 +1,6 @@
-use macroquad::prelude::Rect;
+use macroquad::prelude::{Rect, Vec2};
+
+use crate::enemy::EnemyId;
 
 #[derive(Debug)]
 pub enum AttackType {
@@ -7,6 +9,146 @@ pub enum AttackType {
     RepeatHeavy,
     AttackDash{timer: i32, dir: f32},
     AttackAirDash{timer: i32, dir: f32},
+    /// Up + C, over the head
+    UpSlash,
+    /// V in the air, in front
+    AirSlash,
+    /// Down + V in the air, bouncing off what it hits
+    DownPlunge,
+    /// C held down, for `frames` so far
+    Charging{frames: i32},
+    /// C released after reaching a charge level
+    ChargedHeavy{level: i32},
+}
+
+// Frames of a dash
+pub const DASH_TIME: i32 = 10;
+
+// Frames of charge for each level of the charged heavy
+pub const CHARGE_LEVEL_TIME: i32 = 30;
+pub const MAX_CHARGE_LEVEL: i32 = 3;
+
+/// Charge level reached after holding C for `frames`
+pub fn charge_level(frames: i32) -> i32 {
+    (frames / CHARGE_LEVEL_TIME).min(MAX_CHARGE_LEVEL)
+}
+
+impl AttackType {
+    /// Name used in the combo script
+    pub fn name(&self) -> &'static str {
+        match self {
+            AttackType::Heavy => "heavy",
+            AttackType::Double => "double",
+            AttackType::RepeatHeavy => "repeat_heavy",
+            AttackType::AttackDash { .. } => "dash",
+            AttackType::AttackAirDash { .. } => "air_dash",
+            AttackType::UpSlash => "up_slash",
+            AttackType::AirSlash => "air_slash",
+            AttackType::DownPlunge => "down_plunge",
+            AttackType::Charging { .. } => "charging",
+            AttackType::ChargedHeavy { .. } => "charged_heavy",
+        }
+    }
+
+    pub fn from_name(name: &str, dir: f32) -> Option<Self> {
+        match name {
+            "heavy" => Some(AttackType::Heavy),
+            "double" => Some(AttackType::Double),
+            "repeat_heavy" => Some(AttackType::RepeatHeavy),
+            "dash" => Some(AttackType::AttackDash { timer: DASH_TIME, dir }),
+            "air_dash" => Some(AttackType::AttackAirDash { timer: DASH_TIME, dir }),
+            "up_slash" => Some(AttackType::UpSlash),
+            "air_slash" => Some(AttackType::AirSlash),
+            "down_plunge" => Some(AttackType::DownPlunge),
+            _ => None,
+        }
+    }
+}
+
+/// How often a single attack can land
+pub struct HitRules {
+    /// Hits on the same enemy
+    pub max_hits: i32,
+    /// Frames between two hits on the same enemy
+    pub interval: i32,
+    /// Different enemies the attack goes through
+    pub pierce: i32,
+}
+
+pub fn get_hit_rules(attack: &AttackType) -> HitRules {
+    match attack {
+        // One hit for each of the two slashes
+        AttackType::Double => HitRules { max_hits: 2, interval: 10, pierce: 2 },
+        AttackType::Heavy | AttackType::RepeatHeavy | AttackType::UpSlash => HitRules { max_hits: 1, interval: 0, pierce: 3 },
+        AttackType::ChargedHeavy { level } => HitRules { max_hits: 1, interval: 0, pierce: 3 + level },
+        AttackType::AttackDash { .. } | AttackType::AttackAirDash { .. } => HitRules { max_hits: 1, interval: 0, pierce: 3 },
+        AttackType::AirSlash => HitRules { max_hits: 1, interval: 0, pierce: 2 },
+        AttackType::DownPlunge => HitRules { max_hits: 1, interval: 0, pierce: 1 },
+        AttackType::Charging { .. } => HitRules { max_hits: 0, interval: 0, pierce: 0 },
+    }
+}
+
+/// Enemies hit by the current attack, to follow its `HitRules`
+#[derive(Default)]
+pub struct HitRegistry {
+    /// Number of the current attack, a new one for each attack started
+    pub attack_id: u32,
+    // Frames since the attack started
+    age: i32,
+    // Enemy, hits taken and frame of the last one
+    hits: Vec<(EnemyId, i32, i32)>,
+}
+
+impl HitRegistry {
+    /// Forget every hit, for a new attack
+    pub fn start(&mut self) {
+        self.attack_id += 1;
+        self.age = 0;
+        self.hits.clear();
+    }
+
+    pub fn tick(&mut self) {
+        self.age += 1;
+    }
+
+    pub fn can_hit(&self, target: EnemyId, rules: &HitRules) -> bool {
+        match self.hits.iter().find(|(id, _, _)| *id == target) {
+            Some((_, count, last)) => *count < rules.max_hits && self.age - last >= rules.interval,
+            None => (self.hits.len() as i32) < rules.pierce && rules.max_hits > 0,
+        }
+    }
+
+    pub fn record(&mut self, target: EnemyId) {
+        match self.hits.iter_mut().find(|(id, _, _)| *id == target) {
+            Some((_, count, last)) => {
+                *count += 1;
+                *last = self.age;
+            },
+            None => self.hits.push((target, 1, self.age)),
+        }
+    }
+}
+
+/// What a hit does to the enemy besides the damage
+pub struct HitReaction {
+    /// For a hero facing right
+    pub knockback: Vec2,
+    pub stun: i32,
+    pub launch: bool,
+}
+
+pub fn get_hit_reaction(attack: &AttackType) -> HitReaction {
+    let (x, y, stun, launch) = match attack {
+        AttackType::Double => (1.5, -0.5, 0, false),
+        AttackType::Heavy | AttackType::RepeatHeavy => (6.0, -2.5, 20, true),
+        AttackType::ChargedHeavy { level } => (6.0 + 2.0 * *level as f32, -2.5, 20 + 10 * level, true),
+        AttackType::AttackDash { .. } | AttackType::AttackAirDash { .. } => (2.5, 0.0, 0, false),
+        AttackType::UpSlash => (0.5, -5.0, 10, true),
+        AttackType::AirSlash => (2.0, -1.0, 0, false),
+        AttackType::DownPlunge => (0.0, 3.0, 10, false),
+        AttackType::Charging { .. } => (0.0, 0.0, 0, false),
+    };
+    HitReaction { knockback: Vec2 { x, y }, stun, launch }
 }
 
 pub fn get_hit_point(attack: &AttackType) -> i32 {
@@ -14,6 +156,10 @@ pub fn get_hit_point(attack: &AttackType) -> i32 {
         AttackType::Double | AttackType::AttackAirDash { timer: _, dir: _ } | AttackType::AttackDash { timer: _, dir: _} => 1,
         AttackType::Heavy => 2,
         AttackType::RepeatHeavy => 2,
+        AttackType::UpSlash | AttackType::DownPlunge => 2,
+        AttackType::AirSlash => 1,
+        AttackType::Charging { .. } => 0,
+        AttackType::ChargedHeavy { level } => 2 + level,
     }
 }
 
@@ -41,6 +187,28 @@ pub fn get_hit_box(attack: &AttackType, frame: i32, flip_x: bool) -> Option<Rect
         AttackType::AttackDash {timer: _, dir: _ } | &AttackType::AttackAirDash {timer: _, dir: _ }=> {
             Some(Rect { x: 36.0, y: 32.0, w: 11.0, h: 14.0 })
         },
+        AttackType::UpSlash => {
+            match frame {
+                1..=3 => Some(Rect { x: 18.0, y: 2.0, w: 30.0, h: 28.0 }),
+                _ => None,
+            }
+        },
+        AttackType::AirSlash => {
+            match frame {
+                0..=2 => Some(Rect { x: 34.0, y: 22.0, w: 24.0, h: 26.0 }),
+                _ => None,
+            }
+        },
+        AttackType::DownPlunge => {
+            Some(Rect { x: 24.0, y: 42.0, w: 16.0, h: 14.0 })
+        },
+        AttackType::Charging { .. } => None,
+        AttackType::ChargedHeavy { .. } => {
+            match frame {
+                12..=14 => Some(Rect { x: 28.0, y: 0.0, w: 36.0, h: 48.0 }),
+                _ => None,
+            }
+        },
     };
 
 
@@ -56,4 +224,75 @@ pub fn get_hit_box(attack: &AttackType, frame: i32, flip_x: bool) -> Option<Rect
         }
         _ => None
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> HitRegistry {
+        let mut hits = HitRegistry::default();
+        hits.start();
+        hits
+    }
+
+    fn wait(hits: &mut HitRegistry, frames: i32) {
+        for _ in 0..frames {
+            hits.tick();
+        }
+    }
+
+    #[test]
+    fn single_hit_attack_hits_once() {
+        let rules = get_hit_rules(&AttackType::Heavy);
+        let mut hits = registry();
+        assert!(hits.can_hit(1, &rules));
+        hits.record(1);
+        wait(&mut hits, 30);
+        assert!(!hits.can_hit(1, &rules));
+    }
+
+    #[test]
+    fn double_hits_again_after_the_interval() {
+        let rules = get_hit_rules(&AttackType::Double);
+        let mut hits = registry();
+        hits.record(1);
+        wait(&mut hits, rules.interval - 1);
+        assert!(!hits.can_hit(1, &rules));
+        hits.tick();
+        assert!(hits.can_hit(1, &rules));
+        hits.record(1);
+        wait(&mut hits, rules.interval);
+        assert!(!hits.can_hit(1, &rules));
+    }
+
+    #[test]
+    fn pierce_limits_the_enemies_hit() {
+        let rules = get_hit_rules(&AttackType::DownPlunge);
+        let mut hits = registry();
+        hits.record(1);
+        assert!(!hits.can_hit(2, &rules));
+
+        let rules = get_hit_rules(&AttackType::AirSlash);
+        let mut hits = registry();
+        hits.record(1);
+        assert!(hits.can_hit(2, &rules));
+        hits.record(2);
+        assert!(!hits.can_hit(3, &rules));
+    }
+
+    #[test]
+    fn new_attack_forgets_the_hits() {
+        let rules = get_hit_rules(&AttackType::Heavy);
+        let mut hits = registry();
+        hits.record(1);
+        hits.start();
+        assert!(hits.can_hit(1, &rules));
+    }
+
+    #[test]
+    fn charging_never_hits() {
+        let rules = get_hit_rules(&AttackType::Charging { frames: 0 });
+        assert!(!registry().can_hit(1, &rules));
+    }
+}
@@ -0,0 +1,177 @@
+use macroquad::prelude::{is_key_pressed, KeyCode};
+
+use super::attack::AttackType;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ComboInput {
+    C,
+    V,
+    /// Direction + V
+    Dash,
+}
+
+impl ComboInput {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "c" => Some(ComboInput::C),
+            "v" => Some(ComboInput::V),
+            "dash" => Some(ComboInput::Dash),
+            _ => None,
+        }
+    }
+
+    /// The attack button pressed this frame, if any
+    pub fn read(direction: f32) -> Option<Self> {
+        Self::from_keys(is_key_pressed(KeyCode::C), is_key_pressed(KeyCode::V), direction)
+    }
+
+    fn from_keys(c: bool, v: bool, direction: f32) -> Option<Self> {
+        if c {
+            Some(ComboInput::C)
+        }
+        else if v {
+            Some(if direction != 0.0 {ComboInput::Dash} else {ComboInput::V})
+        }
+        else {
+            None
+        }
+    }
+}
+
+/// An edge of the combo graph
+#[derive(Clone, Debug)]
+pub struct ComboLink {
+    pub from: String,
+    pub input: ComboInput,
+    pub to: String,
+    pub first_frame: i32,
+    pub last_frame: i32,
+    pub finisher: bool,
+}
+
+#[derive(Debug)]
+pub struct ComboGraph {
+    links: Vec<ComboLink>,
+    /// Frames without a hit before the counter goes back to zero
+    pub timeout: i32,
+    /// Hits needed for each extra damage point
+    pub bonus_every: i32,
+}
+
+impl ComboGraph {
+    /// Read a combo script (see assets/data/combos.txt for the format)
+    pub fn parse(script: &str) -> Result<Self, String> {
+        let mut graph = Self { links: Vec::new(), timeout: 90, bonus_every: 4 };
+
+        for (n, raw) in script.lines().enumerate() {
+            let line = raw.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let err = |msg: &str| format!("combos line {}: {}", n + 1, msg);
+            let args: Vec<&str> = line.split_whitespace().collect();
+            let number = |i: usize| -> Result<i32, String> {
+                args.get(i)
+                    .and_then(|a| a.parse::<i32>().ok())
+                    .ok_or_else(|| err("missing or invalid number"))
+            };
+
+            match args[0] {
+                "timeout" => {
+                    graph.timeout = number(1)?;
+                    if graph.timeout < 1 {
+                        return Err(err("timeout below 1"));
+                    }
+                },
+                "bonus_every" => graph.bonus_every = number(1)?.max(1),
+                "link" => {
+                    let from = args.get(1).ok_or_else(|| err("missing attack"))?;
+                    let input = args.get(2)
+                        .and_then(|a| ComboInput::from_name(a))
+                        .ok_or_else(|| err("unknown input"))?;
+                    let to = args.get(3).ok_or_else(|| err("missing attack"))?;
+                    if AttackType::from_name(from, 1.0).is_none() || AttackType::from_name(to, 1.0).is_none() {
+                        return Err(err("unknown attack"));
+                    }
+                    graph.links.push(ComboLink {
+                        from: from.to_string(),
+                        input,
+                        to: to.to_string(),
+                        first_frame: number(4)?,
+                        last_frame: number(5)?,
+                        finisher: args.get(6) == Some(&"finisher"),
+                    });
+                },
+                _ => return Err(err("unknown keyword")),
+            }
+        }
+        Ok(graph)
+    }
+
+    /// The link followed by pressing `input` at `frame` of the attack `from`
+    pub fn follow(&self, from: &str, input: ComboInput, frame: i32) -> Option<&ComboLink> {
+        self.links.iter().find(|l| {
+            l.from == from && l.input == input && (l.first_frame..=l.last_frame).contains(&frame)
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(script: &str) -> ComboGraph {
+        ComboGraph::parse(script).unwrap()
+    }
+
+    #[test]
+    fn parse_bundled_script() {
+        let combos = graph(include_str!("../../assets/data/combos.txt"));
+        assert_eq!(combos.timeout, 90);
+        assert_eq!(combos.bonus_every, 4);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |script: &str| ComboGraph::parse(script).unwrap_err();
+        assert_eq!(error("timeout 0"), "combos line 1: timeout below 1");
+        assert_eq!(error("timeout -10"), "combos line 1: timeout below 1");
+        assert_eq!(error("timeout soon"), "combos line 1: missing or invalid number");
+        assert_eq!(error("link heavy c"), "combos line 1: missing attack");
+        assert_eq!(error("link heavy x heavy 1 2"), "combos line 1: unknown input");
+        assert_eq!(error("link heavy c uppercut 1 2"), "combos line 1: unknown attack");
+        assert_eq!(error("link heavy c heavy 1"), "combos line 1: missing or invalid number");
+        assert_eq!(error("link heavy c heavy one 2"), "combos line 1: missing or invalid number");
+        assert_eq!(error("\ncombo heavy"), "combos line 2: unknown keyword");
+    }
+
+    #[test]
+    fn follow_inside_the_window_only() {
+        let combos = graph("link double c heavy 15 18");
+        assert!(combos.follow("double", ComboInput::C, 14).is_none());
+        assert!(combos.follow("double", ComboInput::C, 15).is_some());
+        assert!(combos.follow("double", ComboInput::C, 18).is_some());
+        assert!(combos.follow("double", ComboInput::C, 19).is_none());
+    }
+
+    #[test]
+    fn follow_matches_attack_and_input() {
+        let combos = graph("link double c heavy 15 18");
+        assert!(combos.follow("heavy", ComboInput::C, 16).is_none());
+        assert!(combos.follow("double", ComboInput::V, 16).is_none());
+        assert!(!combos.follow("double", ComboInput::C, 16).unwrap().finisher);
+    }
+
+    #[test]
+    fn heavy_cancels_into_a_dash_finisher() {
+        let combos = graph(include_str!("../../assets/data/combos.txt"));
+        // V with a direction held during the heavy, where the hero stands still
+        let input = ComboInput::from_keys(false, true, 1.0).unwrap();
+        assert_eq!(input, ComboInput::Dash);
+        let link = combos.follow("heavy", input, 13).unwrap();
+        assert_eq!(link.to, "dash");
+        assert!(link.finisher);
+        assert_eq!(ComboInput::from_keys(false, true, 0.0), Some(ComboInput::V));
+    }
+}
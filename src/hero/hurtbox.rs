@@ -0,0 +1,52 @@
+use macroquad::prelude::Rect;
+
+use crate::collision::mirror_x;
+use super::state::State;
+
+// Standing pose
+const BODY: Rect = Rect { x: 27.0, y: 28.0, w: 10.0, h: 20.0 };
+// Knees bent, before a jump, on landing or in a low swing
+const CROUCH: Rect = Rect { x: 27.0, y: 33.0, w: 11.0, h: 15.0 };
+// Stretched up in the air
+const STRETCH: Rect = Rect { x: 28.0, y: 24.0, w: 9.0, h: 22.0 };
+// Thrown forward by a dash
+const LUNGE: Rect = Rect { x: 23.0, y: 32.0, w: 18.0, h: 14.0 };
+
+/// Box where the hero can be hurt, for a frame of the animation of `state`
+pub fn get_hurt_box(state: State, frame: i32, flip_x: bool) -> Rect {
+    let h_box = match state {
+        State::Jump => {
+            match frame {
+                2..=4 | 9..=11 => CROUCH,
+                5..=8 => STRETCH,
+                _ => BODY,
+            }
+        },
+        State::DoubleJump => STRETCH,
+        State::WallJump => {
+            match frame {
+                0..=2 => CROUCH,
+                _ => STRETCH,
+            }
+        },
+        State::AttackOne | State::ChargedHeavy => {
+            match frame {
+                5..=11 => CROUCH,
+                _ => BODY,
+            }
+        },
+        State::RepeatAttack => {
+            match frame {
+                0 | 1 => CROUCH,
+                _ => BODY,
+            }
+        },
+        State::Dash | State::AirDash => LUNGE,
+        State::Parry => CROUCH,
+        State::DownPlunge => Rect { x: 27.0, y: 30.0, w: 10.0, h: 18.0 },
+        _ => BODY,
+    };
+
+    // Make symetric box if sprite is flip
+    if flip_x {mirror_x(h_box, 64.0)} else {h_box}
+}
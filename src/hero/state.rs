@@ -1,13 +1,17 @@
 use macroquad::rand::gen_range;
 
 use crate::{hero::Hero, sound_system::{SoundBox, SoundList}};
-use super::attack::AttackType;
+use super::attack::{AttackType, CHARGE_LEVEL_TIME};
+use super::INVULNERABILITY;
 
 #[derive(PartialEq, Eq, Copy, Clone, Hash)]
 pub enum State {
     Idle,
     Walk,
     Jump,
+    DoubleJump,
+    WallSlide,
+    WallJump,
     AttackOne,
     AttackDouble,
     RepeatAttack,
@@ -15,7 +19,14 @@ pub enum State {
     AirDash,
     Dying,
     Dead,
-    Hit
+    Hit,
+    Parry,
+    ParryRecover,
+    UpSlash,
+    AirSlash,
+    DownPlunge,
+    Charge,
+    ChargedHeavy,
 }
 
 
@@ -27,13 +38,46 @@ pub fn state_manager(&mut self, sound_bank: &SoundBox) {
 
     let previous_state = self.state;
 
+    // A combo link was followed, whatever the attack
+    if self.cancelled {
+        self.cancelled = false;
+        if let Some(attack) = &self.attack {
+            self.state = attack_state(attack);
+            self.sprite.set_animation(self.animations.get(&self.state).expect("No animation"));
+            self.sprite.play();
+        }
+    }
+    if self.attack.is_none() {
+        self.finisher = false;
+    }
+
+    // Jumps from the air
+    if self.wall_jumped || self.double_jumped {
+        if self.attack.is_none() && !self.hited {
+            self.state = if self.wall_jumped {State::WallJump} else {State::DoubleJump};
+            self.sprite.set_animation(self.animations.get(&self.state).expect("No animation"));
+            self.sprite.play();
+        }
+        self.wall_jumped = false;
+        self.double_jumped = false;
+    }
+
     if self.hited {
         self.state = State::Hit;
         self.hitable = false;
         self.hited = false;
+        self.parry_timer = 0;
+        // A hit breaks the attack, whatever it was
+        self.attack = None;
+        self.finisher = false;
     }
 
-    if self.health <= 0 && self.on_the_floor{
+    // Parry from the ground, out of any attack
+    if self.parry_timer > 0 && matches!(self.state, State::Idle | State::Walk) {
+        self.state = State::Parry;
+    }
+
+    if self.health <= 0 && self.body.grounded{
         self.state = State::Dying;
     }
 
@@ -43,44 +87,36 @@ pub fn state_manager(&mut self, sound_bank: &SoundBox) {
                 self.state = State::Walk;
             }
 
-            if !self.on_the_floor {
+            if !self.body.grounded {
                 self.state = State::Jump;
             };
-            match &self.attack {
-                Some(a) => {
-                    match a {
-                        AttackType::Double => {self.state = State::AttackDouble},
-                        AttackType::Heavy => {self.state = State::AttackOne},
-                        _ => {}
-                    }
-                },
-                None => {}
-            };
+            // Any attack left on the ground plays out, air ones included
+            if let Some(a) = &self.attack {
+                self.state = attack_state(a);
+            }
         },
         State::Walk => {
             if self.direction == 0.0 {self.state = State::Idle;}
-            if !self.on_the_floor {self.state = State::Jump;}
-            match &self.attack {
-                Some(a) => {
-                    match a {
-                        AttackType::Double => {self.state = State::AttackDouble},
-                        AttackType::Heavy => {self.state = State::AttackOne},
-                        AttackType::AttackDash{timer: _, dir: _} => {
-                            self.state = State::Dash;
-                        }
-                        _ => {}
-                    }
-                },
-                None => {}
-            };
+            if !self.body.grounded {self.state = State::Jump;}
+            // Any attack left on the ground plays out, air ones included
+            if let Some(a) = &self.attack {
+                self.state = attack_state(a);
+            }
         },
-        State::Jump => {
-            if self.on_the_floor {self.state = State::Idle}
+        State::Jump | State::DoubleJump | State::WallJump | State::WallSlide => {
+            if self.is_wall_sliding() {
+                self.state = State::WallSlide;
+            }
+            else if self.state == State::WallSlide {
+                self.state = State::Jump;
+            }
+            if self.body.grounded {self.state = State::Idle}
             match &self.attack {
                 Some(a) => {
                     match a {
                         AttackType::Double => {self.state = State::AttackDouble},
                         AttackType::AttackAirDash { timer: _, dir: _ } => {self.state = State::AirDash},
+                        AttackType::UpSlash | AttackType::AirSlash | AttackType::DownPlunge => {self.state = attack_state(a)},
                         _ => {}
                     }
                 },
@@ -96,26 +132,69 @@ pub fn state_manager(&mut self, sound_bank: &SoundBox) {
                 sound_bank.play(SoundList::Sword2);
 
             }
-            self.velocity.x = 0.0;
+            self.body.velocity.x = 0.0;
             if self.sprite.is_animation_ended() {
                 self.state = State::Idle;
                 self.attack = None;
             }
         },
         
-        State::AttackOne | State::RepeatAttack => {
+        State::Charge => {
+            self.body.velocity.x = 0.0;
+            if let Some(AttackType::Charging { frames }) = self.attack {
+                // A higher note for each level reached
+                if frames % CHARGE_LEVEL_TIME == 0 {
+                    match frames / CHARGE_LEVEL_TIME {
+                        1 => sound_bank.play(SoundList::Charge1),
+                        2 => sound_bank.play(SoundList::Charge2),
+                        3 => sound_bank.play(SoundList::Charge3),
+                        _ => {}
+                    }
+                }
+            }
+        },
+
+        State::AttackOne | State::RepeatAttack | State::ChargedHeavy => {
             if self.sprite.current_frame == 0 {
                 sound_bank.play(SoundList::Heavy);
             }
-            if let Some(attack) = &self.attack {
-                match attack {
-                    AttackType::RepeatHeavy => self.state = State::RepeatAttack,
-                    _ => {}
-                }
-                
+            self.body.velocity.x = 0.0;
+            if self.sprite.is_animation_ended() {
+                self.state = State::Idle;
+                self.attack = None;
+            }
+        },
+
+        State::UpSlash => {
+            if self.sprite.current_frame == 0 {
+                sound_bank.play(SoundList::Sword1);
+            }
+            if self.body.grounded {
+                self.body.velocity.x = 0.0;
             }
-            self.velocity.x = 0.0;
             if self.sprite.is_animation_ended() {
+                self.state = if self.body.grounded {State::Idle} else {State::Jump};
+                self.attack = None;
+            }
+        },
+
+        State::AirSlash => {
+            if self.sprite.current_frame == 0 {
+                sound_bank.play(SoundList::Sword2);
+            }
+            if self.sprite.is_animation_ended() {
+                self.state = if self.body.grounded {State::Idle} else {State::Jump};
+                self.attack = None;
+            }
+        },
+
+        State::DownPlunge => {
+            if self.attack.is_none() {
+                // Bounced off an enemy
+                self.state = State::Jump;
+            }
+            else if self.body.grounded {
+                sound_bank.play(SoundList::Heavy);
                 self.state = State::Idle;
                 self.attack = None;
             }
@@ -136,7 +215,7 @@ pub fn state_manager(&mut self, sound_bank: &SoundBox) {
         },
 
         State::AirDash => {
-            if self.on_the_floor {
+            if self.body.grounded {
                 self.state = State::Idle;
                 self.attack = None;
             }
@@ -170,7 +249,27 @@ pub fn state_manager(&mut self, sound_bank: &SoundBox) {
             if self.sprite.is_animation_ended() {
                 self.state = State::Idle;
                 self.hited = false;
-                self.hitable = true;
+                // Still out of reach for a moment
+                self.invulnerable = INVULNERABILITY;
+            }
+        },
+        State::Parry => {
+            self.body.velocity.x = 0.0;
+            if self.parry_timer > 0 {
+                self.parry_timer -= 1;
+                if self.parry_timer == 0 && !self.parried {
+                    // Whiffed: open to punishment
+                    self.state = State::ParryRecover;
+                }
+            }
+            if self.sprite.is_animation_ended() {
+                self.state = State::Idle;
+            }
+        },
+        State::ParryRecover => {
+            self.body.velocity.x = 0.0;
+            if self.sprite.is_animation_ended() {
+                self.state = State::Idle;
             }
         },
         State::Dying => {
@@ -197,4 +296,20 @@ pub fn state_manager(&mut self, sound_bank: &SoundBox) {
         self.sprite.play();
     }
 }
-}
\ No newline at end of file
+}
+
+/// The state playing an attack
+fn attack_state(attack: &AttackType) -> State {
+    match attack {
+        AttackType::Heavy => State::AttackOne,
+        AttackType::RepeatHeavy => State::RepeatAttack,
+        AttackType::Double => State::AttackDouble,
+        AttackType::AttackDash { .. } => State::Dash,
+        AttackType::AttackAirDash { .. } => State::AirDash,
+        AttackType::UpSlash => State::UpSlash,
+        AttackType::AirSlash => State::AirSlash,
+        AttackType::DownPlunge => State::DownPlunge,
+        AttackType::Charging { .. } => State::Charge,
+        AttackType::ChargedHeavy { .. } => State::ChargedHeavy,
+    }
+}
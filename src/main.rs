@@ -9,11 +9,23 @@ use sound_system::SoundBox;
 mod sound_system;
 mod game;
 mod hero;
+mod enemy;
 mod ghost;
+mod charger;
+mod spirit;
+mod boss;
+mod projectile;
 mod sprite;
 mod particle;
 mod light;
 mod controls;
+mod collision;
+mod physics;
+mod steering;
+mod wave;
+mod beat;
+mod score;
+mod difficulty;
 
 
 
@@ -0,0 +1,79 @@
+use macroquad::prelude::*;
+
+use crate::collision::{move_and_collide, Collider, Movement};
+
+pub const GRAVITY: f32 = 0.5;
+
+/// Everything needed to move an entity through the level
+pub struct Body {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub gravity_scale: f32,
+    /// Part of the velocity kept after each step
+    pub drag: Vec2,
+    /// Fastest fall allowed, gravity included
+    pub max_fall_speed: Option<f32>,
+    /// Box used against the level, relative to the position
+    pub collision_box: Rect,
+    pub grounded: bool,
+    /// Ignore one-way platforms during the next step
+    pub drop_through: bool,
+}
+
+impl Body {
+    pub fn new(position: Vec2, collision_box: Rect, gravity_scale: f32, drag: Vec2) -> Self {
+        Self {
+            position,
+            velocity: Vec2::ZERO,
+            gravity_scale,
+            drag,
+            max_fall_speed: None,
+            collision_box,
+            grounded: false,
+            drop_through: false,
+        }
+    }
+
+    /// Apply gravity, move against the colliders and damp the velocity
+    pub fn step(&mut self, colliders: &[Collider]) -> Movement {
+        self.velocity.y += GRAVITY * self.gravity_scale;
+        if let Some(max) = self.max_fall_speed {
+            self.velocity.y = self.velocity.y.min(max);
+        }
+
+        let movement = move_and_collide(self.get_collision_box(0.0, 0.0), self.velocity, colliders, self.drop_through);
+        if movement.wall_normal().is_some() {
+            self.velocity.x = 0.0;
+        }
+        if movement.hit_ceiling() || (movement.grounded && self.velocity.y > 0.0) {
+            self.velocity.y = 0.0;
+        }
+
+        self.position += movement.delta;
+        self.velocity *= self.drag;
+        self.grounded = movement.grounded;
+        self.drop_through = false;
+
+        movement
+    }
+
+    pub fn get_collision_box(&self, dx: f32, dy: f32) -> Rect {
+        Rect { x: self.position.x + self.collision_box.x + dx, y: self.position.y + self.collision_box.y + dy, w: self.collision_box.w, h: self.collision_box.h }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fall_speed_is_capped_after_gravity() {
+        let mut body = Body::new(Vec2::ZERO, Rect::new(0.0, 0.0, 10.0, 10.0), 1.0, Vec2::ONE);
+        body.velocity.y = 1.0;
+        body.max_fall_speed = Some(1.0);
+        let movement = body.step(&[]);
+        assert_eq!(movement.delta.y, 1.0);
+        assert_eq!(body.velocity.y, 1.0);
+    }
+}
@@ -0,0 +1,54 @@
+use macroquad::prelude::*;
+
+use crate::collision::{Collider, ColliderKind};
+
+// Frames before a projectile fades away on its own
+const LIFE_TIME: i32 = 600;
+// Breath taken from the hero by an orb
+pub const DAMAGE: i32 = 120;
+
+/// A slow orb of spirit energy
+pub struct Projectile {
+    pub position: Vec2,
+    velocity: Vec2,
+    pub radius: f32,
+    life: i32,
+    active: bool,
+}
+
+impl Projectile {
+    pub fn new(position: Vec2, velocity: Vec2) -> Self {
+        Self {
+            position,
+            velocity,
+            radius: 3.0,
+            life: LIFE_TIME,
+            active: true,
+        }
+    }
+
+    pub fn update(&mut self, colliders: &[Collider]) {
+        self.position += self.velocity;
+        self.life -= 1;
+
+        // Only solid walls stop it, it flies through the platforms
+        let hit_wall = colliders.iter()
+            .filter(|c| c.kind == ColliderKind::Solid)
+            .any(|c| c.rect.overlaps(&self.get_collision_box()));
+        if hit_wall || self.life <= 0 {
+            self.active = false;
+        }
+    }
+
+    pub fn get_collision_box(&self) -> Rect {
+        Rect { x: self.position.x - self.radius, y: self.position.y - self.radius, w: 2.0 * self.radius, h: 2.0 * self.radius }
+    }
+
+    pub fn destroy(&mut self) {
+        self.active = false;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+}
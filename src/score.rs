@@ -0,0 +1,104 @@
+use crate::difficulty::Difficulty;
+use crate::game::GameMode;
+
+const TABLE_SIZE: usize = 5;
+
+#[derive(Copy, Clone, Debug)]
+pub struct ScoreEntry {
+    pub points: i32,
+    pub wave: usize,
+    pub difficulty: Difficulty,
+}
+
+/// Best scores, one table per game mode
+pub struct HighScores {
+    story: Vec<ScoreEntry>,
+    endless: Vec<ScoreEntry>,
+}
+
+impl HighScores {
+    pub fn load() -> Self {
+        let mut scores = Self { story: Vec::new(), endless: Vec::new() };
+
+        for line in read_scores().lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if let [mode, points, wave, ref rest @ ..] = fields[..] {
+                let mode = match mode {
+                    "story" => GameMode::Story,
+                    "endless" => GameMode::Endless,
+                    _ => continue,
+                };
+                // Scores saved before the presets existed were played in Normal
+                let difficulty = rest.first().and_then(|d| Difficulty::from_key(d)).unwrap_or(Difficulty::Normal);
+                if let (Ok(points), Ok(wave)) = (points.parse(), wave.parse()) {
+                    scores.insert(mode, ScoreEntry { points, wave, difficulty });
+                }
+            }
+        }
+        scores
+    }
+
+    pub fn table(&self, mode: GameMode) -> &[ScoreEntry] {
+        match mode {
+            GameMode::Story => &self.story,
+            GameMode::Endless => &self.endless,
+        }
+    }
+
+    /// Add a score and save the tables. Return the rank (starting at 0) if it made it in the table
+    pub fn record(&mut self, mode: GameMode, entry: ScoreEntry) -> Option<usize> {
+        let rank = self.insert(mode, entry);
+        if rank.is_some() {
+            write_scores(&self.serialize());
+        }
+        rank
+    }
+
+    fn insert(&mut self, mode: GameMode, entry: ScoreEntry) -> Option<usize> {
+        let table = match mode {
+            GameMode::Story => &mut self.story,
+            GameMode::Endless => &mut self.endless,
+        };
+        let rank = table.iter().position(|e| entry.points > e.points).unwrap_or(table.len());
+        if rank >= TABLE_SIZE {
+            return None;
+        }
+        table.insert(rank, entry);
+        table.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+
+    fn serialize(&self) -> String {
+        let mut out = String::new();
+        for (name, table) in [("story", &self.story), ("endless", &self.endless)] {
+            for e in table.iter() {
+                out.push_str(&format!("{} {} {} {}\n", name, e.points, e.wave, e.difficulty.key()));
+            }
+        }
+        out
+    }
+}
+
+
+#[cfg(not(target_arch = "wasm32"))]
+const SCORE_FILE: &str = "kokyu_scores.txt";
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_scores() -> String {
+    std::fs::read_to_string(SCORE_FILE).unwrap_or_default()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_scores(content: &str) {
+    // Losing the table is not worth stopping the game
+    let _ = std::fs::write(SCORE_FILE, content);
+}
+
+// No file system on the web, scores only last for the session
+#[cfg(target_arch = "wasm32")]
+fn read_scores() -> String {
+    String::new()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_scores(_content: &str) {}
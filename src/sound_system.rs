@@ -10,8 +10,13 @@ pub enum SoundList {
     Heavy,
     Sword1,
     Sword2,
+    Charge1,
+    Charge2,
+    Charge3,
+    LastBreath,
     IntroSound,
     Beat,
+    BossAmbiance,
 }
 pub struct SoundBox {
     bank: Vec<Sound>,
@@ -28,6 +33,10 @@ impl SoundBox {
         let heavy_sound = load_sound_from_bytes(include_bytes!("../assets/sounds/sword_heavy.wav")).await.unwrap();
         let sword1_sound = load_sound_from_bytes(include_bytes!("../assets/sounds/sword1.wav")).await.unwrap();
         let sword2_sound = load_sound_from_bytes(include_bytes!("../assets/sounds/sword2.wav")).await.unwrap();
+        let charge1_sound = load_sound_from_bytes(include_bytes!("../assets/sounds/charge_1.wav")).await.unwrap();
+        let charge2_sound = load_sound_from_bytes(include_bytes!("../assets/sounds/charge_2.wav")).await.unwrap();
+        let charge3_sound = load_sound_from_bytes(include_bytes!("../assets/sounds/charge_3.wav")).await.unwrap();
+        let last_breath_sound = load_sound_from_bytes(include_bytes!("../assets/sounds/last_breath.wav")).await.unwrap();
         let mut bank = vec![
             huh1_sound,
             huh2_sound,
@@ -36,7 +45,10 @@ impl SoundBox {
             heavy_sound,
             sword1_sound,
             sword2_sound,
-            
+            charge1_sound,
+            charge2_sound,
+            charge3_sound,
+            last_breath_sound,
         ];
 
         // Add ambiance sound
@@ -51,6 +63,7 @@ impl SoundBox {
         let params = match name {
             SoundList::IntroSound => PlaySoundParams { looped: true, volume: 0.4, },
             SoundList::Beat => PlaySoundParams { looped: true, volume: 0.4, },
+            SoundList::BossAmbiance => PlaySoundParams { looped: true, volume: 0.5, },
             SoundList::Huh1 => PlaySoundParams { looped: false, volume: 0.3, },
             SoundList::Huh2 => PlaySoundParams { looped: false, volume: 0.3, },
             SoundList::Huh3 => PlaySoundParams { looped: false, volume: 0.3, },
@@ -58,6 +71,10 @@ impl SoundBox {
             SoundList::Heavy => PlaySoundParams { looped: false, volume: 0.2, },
             SoundList::Sword1 => PlaySoundParams { looped: false, volume: 0.2, },
             SoundList::Sword2 => PlaySoundParams { looped: false, volume: 0.2, },
+            SoundList::Charge1 => PlaySoundParams { looped: false, volume: 0.2, },
+            SoundList::Charge2 => PlaySoundParams { looped: false, volume: 0.25, },
+            SoundList::Charge3 => PlaySoundParams { looped: false, volume: 0.3, },
+            SoundList::LastBreath => PlaySoundParams { looped: false, volume: 0.4, },
         };
         play_sound(self.bank[name as usize], params)
     }
@@ -74,6 +91,7 @@ impl SoundBox {
 async fn add_ambiance_sound(bank: &mut Vec<Sound>) {
     bank.push(load_sound_from_bytes(include_bytes!("../assets/sounds/amb_intro.ogg")).await.unwrap());
     bank.push(load_sound_from_bytes(include_bytes!("../assets/sounds/heart_beat.ogg")).await.unwrap());
+    bank.push(load_sound_from_bytes(include_bytes!("../assets/sounds/amb_game.ogg")).await.unwrap());
 
 }
 
@@ -81,5 +99,7 @@ async fn add_ambiance_sound(bank: &mut Vec<Sound>) {
 async fn add_ambiance_sound(bank: &mut Vec<Sound>) {
     bank.push(load_sound_from_bytes(include_bytes!("../assets/sounds/amb_intro.mp3")).await.unwrap());
     bank.push(load_sound_from_bytes(include_bytes!("../assets/sounds/heart_beat.mp3")).await.unwrap());
+    // No web version of the game ambiance, the intro one does the job
+    bank.push(load_sound_from_bytes(include_bytes!("../assets/sounds/amb_intro.mp3")).await.unwrap());
 
 }
\ No newline at end of file
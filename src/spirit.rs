@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use macroquad::{prelude::*, rand::gen_range};
+
+use crate::enemy::{Enemy, EnemyEvent, EnemyId, Hit, Surroundings};
+use crate::game::TextureName;
+use crate::physics::Body;
+use crate::projectile::Projectile;
+use crate::sprite::{AnimatedSprite, AnimationData};
+
+#[derive(Eq, PartialEq, Hash, Copy, Clone)]
+enum SpiritState {
+    Float,
+    Cast,
+    Hit,
+    Dead,
+}
+
+// Horizontal distance the spirit tries to keep from the hero
+const PREFERRED_DISTANCE: f32 = 110.0;
+const SPEED: f32 = 0.01;
+// Frames between two shots, and frames of warning before a shot
+const SHOT_COOLDOWN: i32 = 150;
+const CAST_TIME: i32 = 40;
+const PROJECTILE_SPEED: f32 = 1.2;
+const HIT_TIME: i32 = 30;
+// Breath taken from the hero when touched, the orbs hurt more
+const DAMAGE: i32 = 60;
+
+/// A floating spirit that keeps its distance and shoots slow orbs at the hero
+pub struct Spirit {
+    id: EnemyId,
+    body: Body,
+    pub sprite: AnimatedSprite,
+    state: SpiritState,
+    animations: HashMap<SpiritState, AnimationData>,
+
+    health: i32,
+    altitude: f32,
+    timer: i32,
+    cooldown: i32,
+    birth: f32,
+
+    hitable: bool,
+    active: bool,
+    hited: bool,
+}
+
+impl Spirit {
+    pub fn new(id: EnemyId, x: f32, y: f32, health: i32) -> Self {
+        // Float a bit above the spawn point
+        let altitude = (y - gen_range(5.0, 25.0)).clamp(10.0, 45.0);
+        let position = Vec2{x, y: altitude};
+        let animations = HashMap::from([
+            (SpiritState::Float, AnimationData{x: 0, y: 0, h: 64, w: 64, frames: 8, speed: 6, pivot_x: 0, pivot_y: 0}),
+            (SpiritState::Cast, AnimationData{x: 0, y: 0, h: 64, w: 64, frames: 8, speed: 2, pivot_x: 0, pivot_y: 0}),
+            (SpiritState::Hit, AnimationData{x: 0, y: 64, h: 64, w: 64, frames: 3, speed: 10, pivot_x: 0, pivot_y: 0}),
+            (SpiritState::Dead, AnimationData{x: 0, y: 64, h: 64, w: 64, frames: 11, speed: 6, pivot_x: 0, pivot_y: 0}),
+        ]);
+
+        let state = SpiritState::Float;
+        let mut sprite = AnimatedSprite::new(animations.get(&state).expect("No animation in library"));
+        sprite.set_position_to(position);
+        sprite.set_transparency(0.0);
+
+        Self {
+            id,
+            // No gravity, and a strong drag to float smoothly
+            body: Body::new(position, Rect { x: 25.0, y: 27.0, w: 12.0, h: 10.0 }, 0.0, Vec2 { x: 0.9, y: 0.9 }),
+            sprite,
+            state,
+            animations,
+
+            health,
+            altitude,
+            timer: 0,
+            cooldown: SHOT_COOLDOWN,
+            birth: 0.0,
+
+            hitable: false,
+            active: true,
+            hited: false,
+        }
+    }
+
+    fn state_manager(&mut self, hero_pos: Vec2) -> Option<Projectile> {
+        let previous_state = self.state;
+        let mut shot = None;
+
+        match self.state {
+            SpiritState::Float => {
+                // Keep the distance: come closer when too far, back away when too close
+                let dx = self.body.position.x - hero_pos.x;
+                let side = if dx == 0.0 {1.0} else {dx.signum()};
+                let target = Vec2 { x: (hero_pos.x + side * PREFERRED_DISTANCE).clamp(10.0, 360.0), y: self.altitude };
+                self.body.velocity += SPEED * (target - self.body.position).clamp_length_max(20.0);
+
+                if self.cooldown > 0 {
+                    self.cooldown -= 1;
+                }
+                else if self.hitable {
+                    self.state = SpiritState::Cast;
+                    self.timer = CAST_TIME;
+                }
+            },
+            SpiritState::Cast => {
+                // Stop and shine before shooting
+                self.body.velocity *= 0.5;
+                self.sprite.set_transparency(0.6 + 0.4 * (self.timer as f32 * 0.8).cos());
+                self.timer -= 1;
+                if self.timer <= 0 {
+                    self.sprite.set_transparency(1.0);
+                    let origin = self.get_collision_box(0.0, 0.0).center();
+                    // Aim at the middle of the hero body
+                    let aim = hero_pos + Vec2 { x: 32.0, y: 38.0 } - origin;
+                    shot = Some(Projectile::new(origin, PROJECTILE_SPEED * aim.normalize_or_zero()));
+                    self.state = SpiritState::Float;
+                    self.cooldown = SHOT_COOLDOWN + gen_range(0, 60);
+                }
+            },
+            SpiritState::Hit => {
+                self.timer -= 1;
+                if self.timer <= 0 {
+                    self.state = SpiritState::Float;
+
+                    if self.health <= 0 {
+                        self.state = SpiritState::Dead;
+                        self.hitable = false;
+                    }
+                }
+            },
+            SpiritState::Dead => {
+                self.hitable = false;
+                if self.sprite.is_animation_ended() {
+                    self.active = false;
+                }
+            },
+        }
+
+        if self.hited {
+            // A hit cancels the cast
+            self.state = SpiritState::Hit;
+            self.timer = HIT_TIME;
+            self.sprite.set_transparency(1.0);
+            self.hited = false;
+        }
+
+        if previous_state != self.state {
+            self.sprite.set_animation(self.animations.get(&self.state).expect("No animation"));
+            self.sprite.play();
+        }
+        shot
+    }
+
+    pub fn get_collision_box(&self, dx: f32, dy: f32) -> Rect {
+        self.body.get_collision_box(dx, dy)
+    }
+}
+
+impl Enemy for Spirit {
+    fn texture(&self) -> TextureName {
+        TextureName::Spirit
+    }
+
+    fn id(&self) -> EnemyId {
+        self.id
+    }
+
+    /// Shoot when the cast is over
+    fn update(&mut self, surroundings: &Surroundings) -> Vec<EnemyEvent> {
+        let Surroundings { hero_pos, colliders, .. } = *surroundings;
+        if self.birth < 1.0 {
+            self.birth += 0.02;
+            self.sprite.set_transparency(self.birth.min(1.0));
+            self.hitable = self.birth >= 1.0;
+        }
+
+        let shot = self.state_manager(hero_pos);
+
+        self.body.step(colliders);
+        self.sprite.flip_x = hero_pos.x < self.body.position.x;
+        self.sprite.set_position_to(self.body.position);
+
+        shot.into_iter().map(EnemyEvent::Shoot).collect()
+    }
+
+    fn position(&self) -> Vec2 {
+        self.body.position
+    }
+
+    fn hit(&mut self, hit: Hit) {
+        self.hited = true;
+        self.health -= hit.damage;
+    }
+
+    fn is_hitable(&self) -> bool {
+        self.hitable
+    }
+
+    fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn get_hurtboxes(&self) -> Vec<Rect> {
+        vec![self.get_collision_box(0.0, 0.0)]
+    }
+
+    fn is_harmful(&self) -> bool {
+        self.hitable && self.state != SpiritState::Hit
+    }
+
+    /// A parried spirit loses its cast and waits before the next one
+    fn stun(&mut self, frames: i32) {
+        if self.state == SpiritState::Cast {
+            self.state = SpiritState::Float;
+            self.sprite.set_transparency(1.0);
+            self.sprite.set_animation(self.animations.get(&self.state).expect("No animation"));
+            self.sprite.play();
+        }
+        self.cooldown = self.cooldown.max(frames);
+    }
+
+    fn get_damage(&self) -> i32 {
+        DAMAGE
+    }
+
+    fn sprite_mut(&mut self) -> &mut AnimatedSprite {
+        &mut self.sprite
+    }
+}
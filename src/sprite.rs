@@ -110,7 +110,22 @@ impl AnimatedSprite {
         self.current_frame = 0;
     }
 
+    /// Stop the animation on the current frame
+    pub fn pause(&mut self) {
+        self.play = false;
+    }
+
+    /// Go on from the current frame
+    pub fn resume(&mut self) {
+        self.play = true;
+    }
+
     pub fn set_transparency(&mut self, value: f32) {
         self.color.a = value;
     }
+
+    /// Color multiplied to the texture, keeping the transparency
+    pub fn set_tint(&mut self, tint: Color) {
+        self.color = Color { a: self.color.a, ..tint };
+    }
 }
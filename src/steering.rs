@@ -0,0 +1,119 @@
+use macroquad::{prelude::*, rand::gen_range};
+
+// Classic steering behaviours. Each one returns a desired velocity (or a
+// correction to add to it), `apply` then turns the sum into a smooth change
+// of the current velocity.
+
+/// Go straight to the target at full speed
+pub fn seek(position: Vec2, target: Vec2, max_speed: f32) -> Vec2 {
+    (target - position).normalize_or_zero() * max_speed
+}
+
+/// Go straight away from the threat at full speed
+pub fn flee(position: Vec2, threat: Vec2, max_speed: f32) -> Vec2 {
+    -seek(position, threat, max_speed)
+}
+
+/// Like seek, but slow down inside `slowing_radius` to stop on the target
+pub fn arrive(position: Vec2, target: Vec2, max_speed: f32, slowing_radius: f32) -> Vec2 {
+    let offset = target - position;
+    let distance = offset.length();
+    if distance < 0.01 {
+        return Vec2::ZERO;
+    }
+    let speed = max_speed * (distance / slowing_radius).min(1.0);
+    offset / distance * speed
+}
+
+/// Push away from the neighbours closer than `radius`, stronger when closer
+pub fn separation(position: Vec2, neighbours: &[Vec2], radius: f32) -> Vec2 {
+    let mut push = Vec2::ZERO;
+    for other in neighbours.iter() {
+        let offset = position - *other;
+        let distance = offset.length();
+        if distance >= radius {
+            continue;
+        }
+        let away = if distance < 0.01 {
+            // Stacked exactly: pick any way out
+            Vec2 { x: gen_range(-1.0, 1.0), y: gen_range(-1.0, 1.0) }.normalize_or_zero()
+        }
+        else {
+            offset / distance
+        };
+        push += away * (1.0 - distance / radius);
+    }
+    push
+}
+
+/// Pull toward the center of the neighbours closer than `radius`
+pub fn cohesion(position: Vec2, neighbours: &[Vec2], radius: f32, max_speed: f32) -> Vec2 {
+    let close: Vec<Vec2> = neighbours.iter().copied().filter(|p| p.distance(position) < radius).collect();
+    if close.is_empty() {
+        return Vec2::ZERO;
+    }
+    let center = close.iter().fold(Vec2::ZERO, |sum, p| sum + *p) / close.len() as f32;
+    arrive(position, center, max_speed, radius)
+}
+
+/// Push back inside `bounds` when outside of it
+pub fn contain(position: Vec2, bounds: Rect, max_speed: f32) -> Vec2 {
+    let mut push = Vec2::ZERO;
+    if position.x < bounds.left() { push.x = max_speed }
+    else if position.x > bounds.right() { push.x = -max_speed }
+    if position.y < bounds.top() { push.y = max_speed }
+    else if position.y > bounds.bottom() { push.y = -max_speed }
+    push
+}
+
+/// Turn the velocity toward the desired one, with a limited force
+pub fn apply(velocity: Vec2, desired: Vec2, max_force: f32, max_speed: f32) -> Vec2 {
+    let force = (desired - velocity).clamp_length_max(max_force);
+    (velocity + force).clamp_length_max(max_speed)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    #[test]
+    fn arrive_is_full_speed_outside_the_radius() {
+        let v = arrive(Vec2::ZERO, Vec2::new(100.0, 0.0), 2.0, 20.0);
+        assert!((v - Vec2::new(2.0, 0.0)).length() < EPSILON);
+    }
+
+    #[test]
+    fn arrive_slows_inside_the_radius() {
+        let v = arrive(Vec2::ZERO, Vec2::new(5.0, 0.0), 2.0, 20.0);
+        assert!((v - Vec2::new(0.5, 0.0)).length() < EPSILON);
+        assert_eq!(arrive(Vec2::ONE, Vec2::ONE, 2.0, 20.0), Vec2::ZERO);
+    }
+
+    #[test]
+    fn apply_limits_the_force() {
+        let v = apply(Vec2::ZERO, Vec2::new(0.0, 3.0), 0.5, 3.0);
+        assert!((v - Vec2::new(0.0, 0.5)).length() < EPSILON);
+    }
+
+    #[test]
+    fn apply_limits_the_speed() {
+        let v = apply(Vec2::new(3.0, 0.0), Vec2::new(10.0, 0.0), 5.0, 4.0);
+        assert!((v.length() - 4.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn separation_ignores_far_neighbours() {
+        let push = separation(Vec2::ZERO, &[Vec2::new(5.0, 0.0), Vec2::new(50.0, 0.0)], 10.0);
+        assert!((push - Vec2::new(-0.5, 0.0)).length() < EPSILON);
+    }
+
+    #[test]
+    fn contain_pushes_back_inside() {
+        let bounds = Rect::new(0.0, 0.0, 100.0, 50.0);
+        assert_eq!(contain(Vec2::new(-1.0, 60.0), bounds, 2.0), Vec2::new(2.0, -2.0));
+        assert_eq!(contain(Vec2::new(50.0, 25.0), bounds, 2.0), Vec2::ZERO);
+    }
+}
@@ -0,0 +1,400 @@
+use macroquad::{prelude::*, rand::gen_range};
+
+use crate::hero::Ability;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EnemyKind {
+    Ghost,
+    Charger,
+    Spirit,
+    /// The giant ghost of the finale
+    Boss,
+}
+
+impl EnemyKind {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ghost" => Some(EnemyKind::Ghost),
+            "charger" => Some(EnemyKind::Charger),
+            "spirit" => Some(EnemyKind::Spirit),
+            "boss" => Some(EnemyKind::Boss),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum SpawnPoint {
+    Point(Vec2),
+    Area { x_min: f32, x_max: f32, y: f32 },
+}
+
+impl SpawnPoint {
+    fn pick(&self) -> Vec2 {
+        match *self {
+            SpawnPoint::Point(p) => p,
+            SpawnPoint::Area { x_min, x_max, y } => Vec2 { x: gen_range(x_min, x_max), y },
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum NextCondition {
+    AllDead,
+    Timer(i32),
+}
+
+#[derive(Clone, Debug)]
+pub struct WaveDef {
+    pub name: String,
+    pub enemies: Vec<(EnemyKind, i32)>,
+    pub spawn_points: Vec<SpawnPoint>,
+    pub start_delay: i32,
+    pub delay: (i32, i32),
+    pub max_alive: usize,
+    pub next: NextCondition,
+    /// Extra health given to the enemies of the wave
+    pub health_bonus: i32,
+    /// Moves given to the hero when the wave starts
+    pub unlocks: Vec<Ability>,
+}
+
+impl WaveDef {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            enemies: Vec::new(),
+            spawn_points: Vec::new(),
+            start_delay: 5,
+            delay: (50, 80),
+            max_alive: 5,
+            next: NextCondition::AllDead,
+            health_bonus: 0,
+            unlocks: Vec::new(),
+        }
+    }
+
+    /// Procedural wave for the endless mode, harder at each index
+    fn endless(index: usize) -> Self {
+        let level = index as i32;
+        let enemies = ENDLESS_POOL.iter()
+            .filter(|(_, first_wave)| index >= *first_wave)
+            .map(|(kind, first_wave)| (*kind, 3 + (index - first_wave) as i32))
+            .collect();
+
+        Self {
+            name: format!("Endless {}", index + 1),
+            enemies,
+            spawn_points: vec![SpawnPoint::Area { x_min: 50.0, x_max: 380.0, y: 52.0 }],
+            start_delay: 60,
+            delay: ((50 - 4 * level).max(10), (90 - 6 * level).max(20)),
+            max_alive: (3 + index / 2).min(8),
+            next: NextCondition::AllDead,
+            health_bonus: level / 2,
+            unlocks: if index == ENDLESS_DOUBLE_JUMP {vec![Ability::DoubleJump]} else {Vec::new()},
+        }
+    }
+}
+
+// Enemies used by the endless mode, with the first wave they appear in
+const ENDLESS_POOL: [(EnemyKind, usize); 3] = [
+    (EnemyKind::Ghost, 0),
+    (EnemyKind::Charger, 2),
+    (EnemyKind::Spirit, 4),
+];
+
+// Endless wave giving the double jump
+const ENDLESS_DOUBLE_JUMP: usize = 2;
+
+/// Read a wave script (see assets/data/waves.txt for the format)
+pub fn parse_waves(script: &str) -> Result<Vec<WaveDef>, String> {
+    let mut waves: Vec<WaveDef> = Vec::new();
+
+    for (n, raw) in script.lines().enumerate() {
+        let line = raw.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let err = |msg: &str| format!("waves line {}: {}", n + 1, msg);
+        let (key, rest) = line.split_once(' ').unwrap_or((line, ""));
+
+        if key == "wave" {
+            waves.push(WaveDef::new(rest.trim()));
+            continue;
+        }
+
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        let wave = waves.last_mut().ok_or_else(|| err("no wave declared"))?;
+        let number = |i: usize| -> Result<f32, String> {
+            args.get(i)
+                .and_then(|a| a.parse::<f32>().ok())
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| err("missing or invalid number"))
+        };
+
+        match key {
+            "enemy" => {
+                let kind = args.first()
+                    .and_then(|a| EnemyKind::from_name(a))
+                    .ok_or_else(|| err("unknown enemy type"))?;
+                let count = number(1)? as i32;
+                if count < 0 {
+                    return Err(err("negative enemy count"));
+                }
+                wave.enemies.push((kind, count));
+            },
+            "point" => wave.spawn_points.push(SpawnPoint::Point(Vec2 { x: number(0)?, y: number(1)? })),
+            "area" => {
+                let (x_min, x_max) = (number(0)?, number(1)?);
+                if x_min > x_max {
+                    return Err(err("x_min above x_max"));
+                }
+                wave.spawn_points.push(SpawnPoint::Area { x_min, x_max, y: number(2)? });
+            },
+            "start" => {
+                wave.start_delay = number(0)? as i32;
+                if wave.start_delay < 0 {
+                    return Err(err("negative start delay"));
+                }
+            },
+            "delay" => {
+                wave.delay = (number(0)? as i32, number(1)? as i32);
+                if wave.delay.0 > wave.delay.1 {
+                    return Err(err("min delay above max delay"));
+                }
+            },
+            "max_alive" => {
+                // Checked before the cast, which would turn a negative value into 0
+                let max_alive = number(0)?;
+                if max_alive < 1.0 {
+                    return Err(err("max_alive below 1"));
+                }
+                wave.max_alive = max_alive as usize;
+            },
+            "health" => {
+                wave.health_bonus = number(0)? as i32;
+                if wave.health_bonus < 0 {
+                    return Err(err("negative health bonus"));
+                }
+            },
+            "unlock" => {
+                let ability = args.first()
+                    .and_then(|a| Ability::from_name(a))
+                    .ok_or_else(|| err("unknown ability"))?;
+                wave.unlocks.push(ability);
+            },
+            "next" => {
+                wave.next = match args.first() {
+                    Some(&"all_dead") => NextCondition::AllDead,
+                    Some(&"timer") => match number(1)? as i32 {
+                        t if t < 0 => return Err(err("negative timer")),
+                        t => NextCondition::Timer(t),
+                    },
+                    _ => return Err(err("unknown next condition")),
+                }
+            },
+            _ => return Err(err("unknown keyword")),
+        }
+    }
+
+    match waves.iter().find(|w| w.spawn_points.is_empty()) {
+        Some(w) => Err(format!("wave '{}' has no spawn point", w.name)),
+        None => Ok(waves),
+    }
+}
+
+/// Run the waves one after the other and tell the game when to spawn
+pub struct WaveDirector {
+    waves: Vec<WaveDef>,
+    endless: bool,
+    delay_scale: f32,
+    current: usize,
+    pending: Vec<EnemyKind>,
+    spawn_timer: i32,
+    wave_timer: i32,
+}
+
+impl WaveDirector {
+    /// `delay_scale` is applied to every delay between two births
+    pub fn new(waves: Vec<WaveDef>, delay_scale: f32) -> Self {
+        Self::build(waves, false, delay_scale)
+    }
+
+    /// Never ending waves, generated on the fly
+    pub fn endless(delay_scale: f32) -> Self {
+        Self::build(Vec::new(), true, delay_scale)
+    }
+
+    fn build(waves: Vec<WaveDef>, endless: bool, delay_scale: f32) -> Self {
+        let mut director = Self {
+            waves,
+            endless,
+            delay_scale,
+            current: 0,
+            pending: Vec::new(),
+            spawn_timer: 0,
+            wave_timer: 0,
+        };
+        director.start_wave(0);
+        director
+    }
+
+    fn start_wave(&mut self, index: usize) {
+        self.current = index;
+        self.wave_timer = 0;
+        if self.endless && index >= self.waves.len() {
+            self.waves.push(WaveDef::endless(index));
+        }
+        if let Some(wave) = self.waves.get(index) {
+            // Pending enemies are popped from the back
+            self.pending = wave.enemies.iter()
+                .flat_map(|(kind, count)| std::iter::repeat_n(*kind, *count as usize))
+                .rev()
+                .collect();
+            self.spawn_timer = (wave.start_delay as f32 * self.delay_scale) as i32;
+        }
+    }
+
+    /// Advance the script, and return the enemy to spawn this frame, if any
+    pub fn update(&mut self, alive: usize) -> Option<(EnemyKind, Vec2)> {
+        let wave = self.waves.get(self.current)?;
+        self.wave_timer += 1;
+
+        let wave_over = match wave.next {
+            NextCondition::AllDead => self.pending.is_empty() && alive == 0,
+            NextCondition::Timer(t) => self.pending.is_empty() && (alive == 0 || self.wave_timer >= t),
+        };
+        if wave_over {
+            self.start_wave(self.current + 1);
+            return None;
+        }
+
+        self.spawn_timer -= 1;
+        if self.spawn_timer <= 0 && alive < wave.max_alive {
+            let kind = self.pending.pop()?;
+            let point = wave.spawn_points[gen_range(0, wave.spawn_points.len())].pick();
+            self.spawn_timer = (gen_range(wave.delay.0, wave.delay.1 + 1) as f32 * self.delay_scale) as i32;
+            return Some((kind, point));
+        }
+        None
+    }
+
+    /// Current wave number (starting at 1) and name
+    pub fn current_wave(&self) -> Option<(usize, &str)> {
+        self.waves.get(self.current).map(|w| (self.current + 1, w.name.as_str()))
+    }
+
+    /// Number of waves in the script, None for the endless mode
+    pub fn wave_count(&self) -> Option<usize> {
+        if self.endless {None} else {Some(self.waves.len())}
+    }
+
+    pub fn health_bonus(&self) -> i32 {
+        self.waves.get(self.current).map_or(0, |w| w.health_bonus)
+    }
+
+    /// Every move unlocked by the waves started so far
+    pub fn unlocks(&self) -> impl Iterator<Item = Ability> + '_ {
+        self.waves.iter()
+            .take(self.current + 1)
+            .flat_map(|w| w.unlocks.iter().copied())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current >= self.waves.len()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two waves: one born enemy at a time, then a timed one
+    const SCRIPT: &str = "
+wave One  # comment
+enemy ghost 2
+point 10 20
+start 2
+delay 3 3
+max_alive 1
+
+wave Two
+enemy charger 1
+area 0 100 50
+start 0
+next timer 5
+";
+
+    fn parse_error(line: &str) -> String {
+        parse_waves(&format!("wave W\npoint 0 0\n{}", line)).unwrap_err()
+    }
+
+    #[test]
+    fn parse_valid_script() {
+        let waves = parse_waves(SCRIPT).unwrap();
+        assert_eq!(waves.len(), 2);
+        assert_eq!(waves[0].name, "One");
+        assert_eq!(waves[0].enemies, vec![(EnemyKind::Ghost, 2)]);
+        assert_eq!(waves[0].start_delay, 2);
+        assert_eq!(waves[0].delay, (3, 3));
+        assert_eq!(waves[0].max_alive, 1);
+        assert_eq!(waves[0].next, NextCondition::AllDead);
+        assert_eq!(waves[1].next, NextCondition::Timer(5));
+    }
+
+    #[test]
+    fn parse_bundled_script() {
+        assert!(parse_waves(include_str!("../assets/data/waves.txt")).is_ok());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse_error("enemy ghost -1"), "waves line 3: negative enemy count");
+        assert_eq!(parse_error("max_alive 0"), "waves line 3: max_alive below 1");
+        assert_eq!(parse_error("max_alive -2"), "waves line 3: max_alive below 1");
+        assert_eq!(parse_error("delay 80 50"), "waves line 3: min delay above max delay");
+        assert_eq!(parse_error("start -5"), "waves line 3: negative start delay");
+        assert_eq!(parse_error("next timer -1"), "waves line 3: negative timer");
+        assert_eq!(parse_error("enemy dragon 1"), "waves line 3: unknown enemy type");
+        assert_eq!(parse_error("health -1"), "waves line 3: negative health bonus");
+        assert_eq!(parse_error("area 300 100 50"), "waves line 3: x_min above x_max");
+        assert_eq!(parse_error("enemy ghost inf"), "waves line 3: missing or invalid number");
+        assert_eq!(parse_error("start NaN"), "waves line 3: missing or invalid number");
+        assert_eq!(parse_error("point 10"), "waves line 3: missing or invalid number");
+        assert_eq!(parse_waves("enemy ghost 1").unwrap_err(), "waves line 1: no wave declared");
+        assert_eq!(parse_waves("wave Lost\nenemy ghost 1").unwrap_err(), "wave 'Lost' has no spawn point");
+    }
+
+    #[test]
+    fn director_runs_the_waves_in_order() {
+        let mut director = WaveDirector::new(parse_waves(SCRIPT).unwrap(), 1.0);
+        assert_eq!(director.current_wave(), Some((1, "One")));
+
+        // First birth after the start delay
+        assert_eq!(director.update(0), None);
+        assert_eq!(director.update(0), Some((EnemyKind::Ghost, Vec2 { x: 10.0, y: 20.0 })));
+
+        // No birth while the wave is full
+        for _ in 0..10 {
+            assert_eq!(director.update(1), None);
+        }
+        assert!(director.update(0).is_some());
+
+        // Next wave once every enemy is dead
+        assert_eq!(director.update(1), None);
+        assert_eq!(director.current_wave(), Some((1, "One")));
+        assert_eq!(director.update(0), None);
+        assert_eq!(director.current_wave(), Some((2, "Two")));
+
+        // A timed wave ends even with enemies left
+        assert_eq!(director.update(0).map(|(kind, _)| kind), Some(EnemyKind::Charger));
+        for _ in 0..3 {
+            assert_eq!(director.update(1), None);
+        }
+        assert!(!director.is_finished());
+        director.update(1);
+        assert!(director.is_finished());
+        assert_eq!(director.current_wave(), None);
+    }
+}